@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+
+use crate::ast::{ Module, Item, Function, Expr, ExprKind, UnaryOp, Arg, Let, walk_lets };
+use crate::diagnostic::{ Diagnostic, Diagnostics };
+use crate::resolve;
+use crate::token::Span;
+
+type FunctionKey = Vec<String>;
+type TypeKey = Vec<String>;
+
+#[derive(Default)]
+struct Signatures {
+    functions: HashMap<FunctionKey, Function>,
+    methods: HashMap<TypeKey, HashMap<String, Function>>,
+}
+
+struct Context<'a> {
+    scope: &'a [String],
+    signatures: &'a Signatures,
+    self_ty: Option<TypeKey>,
+    bindings: HashMap<String, Option<TypeKey>>,
+}
+
+pub fn resolve_calls(module: &mut Module) -> Diagnostics {
+    let mut signatures = Signatures::default();
+    let mut implemented = vec![];
+    collect_signatures(&module.items, &mut vec![], &mut signatures, &mut implemented);
+    for (scope, trait_name, ty) in implemented {
+        let trait_key = resolve::candidates(&scope, &trait_name)
+            .into_iter()
+            .find(|candidate| signatures.methods.contains_key(candidate));
+        if let Some(functions) = trait_key.map(|trait_key| signatures.methods[&trait_key].clone()) {
+            let methods = signatures.methods.entry(ty).or_default();
+            for (name, function) in functions {
+                methods.entry(name).or_insert(function);
+            }
+        }
+    }
+
+    let mut diagnostics = Diagnostics::new();
+    resolve_items(&mut module.items, &mut vec![], &signatures, &mut diagnostics);
+    diagnostics
+}
+
+fn collect_signatures(
+    items: &[Item],
+    scope: &mut Vec<String>,
+    signatures: &mut Signatures,
+    implemented: &mut Vec<(Vec<String>, Vec<String>, TypeKey)>,
+) {
+    for item in items {
+        match item {
+            Item::Function(function) => {
+                let key = scope.iter().cloned().chain(Some(function.name.clone())).collect();
+                signatures.functions.entry(key).or_insert_with(|| function.clone());
+            },
+            Item::Impl(item) => {
+                let ty: TypeKey = scope.iter().cloned().chain(split_path(&item.self_ty.name)).collect();
+                for function in &item.functions {
+                    let key = ty.iter().cloned().chain(Some(function.name.clone())).collect();
+                    signatures.functions.entry(key).or_insert_with(|| function.clone());
+                }
+                let methods = signatures.methods.entry(ty.clone()).or_default();
+                for function in item.functions.iter().filter(|function| function.self_param.is_some()) {
+                    methods.entry(function.name.clone()).or_insert_with(|| function.clone());
+                }
+                if let Some(trait_name) = &item.trait_name {
+                    implemented.push((scope.clone(), trait_name.segments.clone(), ty));
+                }
+            },
+            Item::Trait(item) => {
+                let key = scope.iter().cloned().chain(Some(item.name.clone())).collect();
+                let methods = signatures.methods.entry(key).or_default();
+                for function in item.functions.iter().filter(|function| function.self_param.is_some()) {
+                    methods.entry(function.name.clone()).or_insert_with(|| function.clone());
+                }
+            },
+            Item::Module(module) => {
+                scope.push(module.name.clone().unwrap_or_default());
+                collect_signatures(&module.items, scope, signatures, implemented);
+                scope.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+fn resolve_items(items: &mut [Item], scope: &mut Vec<String>, signatures: &Signatures, diagnostics: &mut Diagnostics) {
+    let mut context = Context { scope, signatures, self_ty: None, bindings: HashMap::new() };
+    for item in items.iter_mut() {
+        if let Item::Stmt(stmt) = item {
+            walk_lets(std::slice::from_mut(stmt), &mut |binding| context.bind_let(binding));
+        }
+    }
+    for item in items.iter_mut() {
+        if let Item::Stmt(stmt) = item {
+            stmt.walk_mut(&mut |expr| context.resolve_expr(expr, diagnostics));
+        }
+    }
+
+    for item in items {
+        match item {
+            Item::Function(function) => resolve_function(function, None, scope, signatures, diagnostics),
+            Item::Impl(item) => {
+                let ty = find_type(scope, signatures, &split_path(&item.self_ty.name));
+                for function in &mut item.functions {
+                    resolve_function(function, ty.clone(), scope, signatures, diagnostics);
+                }
+            },
+            Item::Trait(item) => {
+                let ty = Some(scope.iter().cloned().chain(Some(item.name.clone())).collect());
+                for function in &mut item.functions {
+                    resolve_function(function, ty.clone(), scope, signatures, diagnostics);
+                }
+            },
+            Item::Module(module) => {
+                scope.push(module.name.clone().unwrap_or_default());
+                resolve_items(&mut module.items, scope, signatures, diagnostics);
+                scope.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+impl Signatures {
+    fn declared<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Function> {
+        let functions = self.functions
+            .iter()
+            .filter(move |(key, _)| key.last().map(String::as_str) == Some(name))
+            .map(|(_, function)| function);
+        functions.chain(self.methods.values().filter_map(move |methods| methods.get(name)))
+    }
+}
+
+fn has_omitted_defaults(function: &Function, args: &[Arg]) -> bool {
+    args.len() < function.params.len() && function.params.iter().any(|param| param.default.is_some())
+}
+
+fn find_type(scope: &[String], signatures: &Signatures, segments: &[String]) -> Option<TypeKey> {
+    resolve::candidates(scope, segments)
+        .into_iter()
+        .find(|candidate| signatures.methods.contains_key(candidate))
+}
+
+fn resolve_function(
+    function: &mut Function,
+    self_ty: Option<TypeKey>,
+    scope: &[String],
+    signatures: &Signatures,
+    diagnostics: &mut Diagnostics,
+) {
+    let self_ty = self_ty.filter(|_| function.self_param.is_some());
+    let body = match &mut function.body {
+        Some(body) => body,
+        None => return,
+    };
+    let mut context = Context { scope, signatures, self_ty, bindings: HashMap::new() };
+    for param in &function.params {
+        let ty = context.find_type(&split_path(&param.ty.name));
+        context.bind(&param.name, ty);
+    }
+    walk_lets(&mut body.stmts, &mut |binding| context.bind_let(binding));
+    body.walk_mut(&mut |expr| context.resolve_expr(expr, diagnostics));
+}
+
+impl<'a> Context<'a> {
+    fn find_type(&self, segments: &[String]) -> Option<TypeKey> {
+        find_type(self.scope, self.signatures, segments)
+    }
+
+    fn bind(&mut self, name: &str, ty: Option<TypeKey>) {
+        let binding = self.bindings.entry(name.to_string()).or_insert_with(|| ty.clone());
+        if *binding != ty {
+            *binding = None;
+        }
+    }
+
+    fn bind_let(&mut self, binding: &Let) {
+        let ty = match &binding.ty {
+            Some(ty) => self.find_type(&split_path(&ty.name)),
+            None => self.type_of(&binding.value),
+        };
+        self.bind(&binding.name, ty);
+    }
+
+    fn type_of(&self, expr: &Expr) -> Option<TypeKey> {
+        match &expr.kind {
+            ExprKind::Path(path) if path.segments == ["self"] => self.self_ty.clone(),
+            ExprKind::Path(path) if path.segments.len() == 1 => self.bindings.get(&path.segments[0]).cloned().flatten(),
+            ExprKind::Struct { path, .. } => self.find_type(&path.segments),
+            ExprKind::Call { callee, .. } => match &callee.kind {
+                ExprKind::Path(path) if path.segments.len() > 1 => {
+                    let (name, ty) = path.segments.split_last()?;
+                    let ty = self.find_type(ty)?;
+                    let key = ty.iter().cloned().chain(Some(name.clone())).collect::<Vec<_>>();
+                    let return_ty = self.signatures.functions.get(&key)?.return_ty.as_ref()?;
+                    if return_ty.name == "Self" || Some(&return_ty.name) == ty.last() {
+                        Some(ty)
+                    } else {
+                        None
+                    }
+                },
+                _ => None,
+            },
+            ExprKind::Unary { op: UnaryOp::Borrow, operand } |
+            ExprKind::Unary { op: UnaryOp::BorrowMut, operand } |
+            ExprKind::Unary { op: UnaryOp::Own, operand } => self.type_of(operand),
+            ExprKind::Paren(expr) => self.type_of(expr),
+            _ => None,
+        }
+    }
+
+    fn resolve_expr(&self, expr: &mut Expr, diagnostics: &mut Diagnostics) {
+        let span = expr.span;
+        let (name, function, args) = match &mut expr.kind {
+            ExprKind::Call { callee, args } => match &callee.kind {
+                ExprKind::Path(path) => {
+                    let function = resolve::candidates(self.scope, &path.segments)
+                        .iter()
+                        .find_map(|candidate| self.signatures.functions.get(candidate));
+                    (path.name().to_string(), function, args)
+                },
+                _ => ("this function".to_string(), None, args),
+            },
+            ExprKind::MethodCall { receiver, method, args, .. } => {
+                let function = self.type_of(receiver)
+                    .and_then(|ty| self.signatures.methods.get(&ty))
+                    .and_then(|methods| methods.get(method.as_str()));
+                (method.clone(), function, args)
+            },
+            _ => return,
+        };
+        match function {
+            Some(function) => bind_arguments(function, args, span, diagnostics),
+            None => {
+                if let Some(arg) = args.iter().find(|arg| arg.name.is_some()) {
+                    let message = format!("cannot resolve named arguments for `{}`", name);
+                    diagnostics.push(Diagnostic::error(&message, arg.span));
+                } else if self.signatures.declared(&name).any(|function| has_omitted_defaults(function, args)) {
+                    let message = format!("cannot resolve `{}` to fill in its default arguments", name);
+                    diagnostics.push(Diagnostic::warning(&message, span));
+                }
+            },
+        }
+    }
+}
+
+fn split_path(name: &str) -> Vec<String> {
+    name.split("::").map(|segment| segment.to_string()).collect()
+}
+
+fn bind_arguments(function: &Function, args: &mut Vec<Arg>, span: Span, diagnostics: &mut Diagnostics) {
+    let (supplied, block) = match &function.block_param {
+        Some(block_param) => match args.split_last() {
+            Some((arg, rest)) if arg.name.is_none() || arg.name.as_ref() == Some(&block_param.name) => (rest, Some(arg)),
+            _ => {
+                let message = format!("missing block `{}` in call to `{}`", block_param.name, function.name);
+                diagnostics.push(Diagnostic::error(&message, span));
+                return
+            },
+        },
+        None => (args.as_slice(), None),
+    };
+    let params = &function.params;
+    let mut slots: Vec<Option<&Arg>> = vec![None; params.len()];
+    let mut valid = true;
+    let mut positional = 0;
+    for arg in supplied {
+        let index = match &arg.name {
+            None => {
+                if positional >= params.len() {
+                    let message = format!(
+                        "`{}` takes {} argument{} but {} were supplied",
+                        function.name, params.len(), if params.len() == 1 { "" } else { "s" }, supplied.len()
+                    );
+                    diagnostics.push(Diagnostic::error(&message, arg.span));
+                    valid = false;
+                    break;
+                }
+                positional += 1;
+                positional - 1
+            },
+            Some(name) => match params.iter().position(|param| &param.name == name) {
+                Some(index) => index,
+                None => {
+                    let message = format!("`{}` has no parameter named `{}`", function.name, name);
+                    let note = format!(
+                        "the parameters of `{}` are {}",
+                        function.name,
+                        params.iter().map(|param| format!("`{}`", param.name)).collect::<Vec<_>>().join(", ")
+                    );
+                    diagnostics.push(Diagnostic::error(&message, arg.span).with_note(&note));
+                    valid = false;
+                    continue
+                },
+            },
+        };
+        if slots[index].is_some() {
+            let message = format!("argument `{}` is supplied more than once", params[index].name);
+            diagnostics.push(Diagnostic::error(&message, arg.span));
+            valid = false;
+            continue
+        }
+        slots[index] = Some(arg);
+    }
+
+    let missing = params
+        .iter()
+        .zip(&slots)
+        .filter(|(param, slot)| slot.is_none() && param.default.is_none())
+        .map(|(param, _)| format!("`{}`", param.name))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        let message = format!("missing argument {} in call to `{}`", missing.join(", "), function.name);
+        diagnostics.push(Diagnostic::error(&message, span));
+        valid = false;
+    }
+    if !valid {
+        return
+    }
+
+    let bound = params
+        .iter()
+        .zip(slots)
+        .map(|(param, slot)| match slot {
+            Some(arg) => Arg { name: None, ..arg.clone() },
+            None => Arg { name: None, value: param.default.clone().unwrap(), span },
+        })
+        .chain(block.map(|block| Arg { name: None, ..block.clone() }))
+        .collect();
+    *args = bound;
+}
+
+#[test]
+fn test_default_arguments() {
+    use crate::parser;
+    use crate::source::FileId;
+    use crate::transpiler::transpile;
+
+    let source = "\
+module greetings do
+  function greet(name as String, greeting as String = \"Hello\", times as Int32 = 1) do
+    println!(\"{} {}\", greeting, name)
+  end
+end
+
+implements Counter do
+  function add(borrow self, amount as Int32 = 1) do
+  end
+
+  function reset(borrow self) do
+    self.add()
+  end
+end
+
+greetings::greet(\"Ada\")
+greetings::greet(\"Ada\", \"Hi\")
+let counter as Counter = Counter::default()
+counter.add()
+let numbers = vec![1]
+numbers.add(4)
+let unknown = make_counter()
+unknown.add()
+greetings::greet()
+greetings::greet(\"Ada\", \"Hi\", 2, 3)
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    let diagnostics = resolve_calls(&mut module);
+    let messages = diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>();
+    assert_eq!(messages, [
+        "cannot resolve `add` to fill in its default arguments",
+        "missing argument `name` in call to `greet`",
+        "`greet` takes 3 arguments but 4 were supplied",
+    ]);
+
+    let rust = transpile(&module);
+    assert!(rust.contains("fn greet(name: String, greeting: String, times: i32) {"));
+    assert!(rust.contains("greetings::greet(\"Ada\", \"Hello\", 1);"));
+    assert!(rust.contains("greetings::greet(\"Ada\", \"Hi\", 1);"));
+    assert!(rust.contains("counter.add(1);"));
+    assert!(rust.contains("self.add(1);"));
+    assert!(rust.contains("numbers.add(4);"));
+    assert!(rust.contains("greetings::greet(\"Ada\", \"Hi\", 2, 3);"));
+
+    let (_, diagnostics) = parser::parse_partial(FileId(0), "function f(a as Int32 = 1, b as Int32) do\nend\n");
+    assert_eq!(
+        diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>(),
+        ["parameter `b` without a default value follows defaulted parameter `a`"]
+    );
+}
+
+#[test]
+fn test_named_arguments() {
+    use crate::parser;
+    use crate::source::FileId;
+    use crate::transpiler::transpile;
+
+    let source = "\
+function area(width as Int32, height as Int32, depth as Int32 = 1) do
+end
+
+area(height: 2, width: 3)
+area(3, depth: 4, height: 2)
+area(3, colour: 2)
+area(3, width: 2, height: 1)
+
+trait Greeting do
+  function greet(borrow self, name as String) as String
+end
+
+implements Greeting for Cat do
+  function greet(borrow self, name as String) as String do
+    name
+  end
+end
+
+let cat as Cat = Cat::default()
+cat.greet(name: \"Tom\")
+stranger.greet(name: \"Tom\")
+volume(width: 1)
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    let diagnostics = resolve_calls(&mut module);
+    let messages = diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>();
+    assert_eq!(messages, [
+        "`area` has no parameter named `colour`",
+        "missing argument `height` in call to `area`",
+        "argument `width` is supplied more than once",
+        "cannot resolve named arguments for `greet`",
+        "cannot resolve named arguments for `volume`",
+    ]);
+
+    let rust = transpile(&module);
+    assert!(rust.contains("area(3, 2, 1);"));
+    assert!(rust.contains("area(3, 2, 4);"));
+    assert!(rust.contains("cat.greet(\"Tom\");"));
+}
+
+#[test]
+fn test_block_arguments() {
+    use crate::parser;
+    use crate::source::FileId;
+    use crate::transpiler::transpile;
+
+    let source = "\
+function repeat(times as Int32 = 2, &block(Int32)) do
+end
+
+repeat do |index| println!(\"{}\", index) end
+repeat(3, fn(index) do println!(\"{}\", index) end)
+repeat(block: fn(index) do println!(\"{}\", index) end)
+repeat(3, times: 4)
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    let diagnostics = resolve_calls(&mut module);
+    let messages = diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>();
+    assert_eq!(messages, ["missing block `block` in call to `repeat`"]);
+
+    let rust = transpile(&module);
+    assert!(rust.contains("repeat(2, |index| println!(\"{}\", index));"));
+    assert!(rust.contains("repeat(3, |index| println!(\"{}\", index));"));
+    assert_eq!(rust.matches("repeat(2, |index|").count(), 2);
+}
@@ -1,9 +1,531 @@
-use crate::token::Token;
+use crate::token::Span;
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
-pub struct Node {
-    pub token: Token,
-    pub children: Vec<Node>,
-    pub data: Option<String>,
+pub const STRUCT_DERIVES: &[&str] = &["Debug", "Clone", "Default", "PartialEq"];
+pub const ENUM_DERIVES: &[&str] = &["Debug", "Clone", "Default", "PartialEq", "Eq", "PartialOrd", "Ord"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Module {
+    pub name: Option<String>,
+    pub public: bool,
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+    Module(Module),
+    Struct(Struct),
+    Enum(Enum),
+    Trait(Trait),
+    Impl(Impl),
+    Function(Function),
+    Use(Use),
+    ExternCrate(ExternCrate),
+    MacroRules(MacroRules),
+    Stmt(Stmt),
+    Error(Span),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Struct {
+    pub name: String,
+    pub public: bool,
+    pub parent: Option<Path>,
+    pub derives: Vec<String>,
+    pub excluded_derives: Vec<String>,
+    pub constructor: bool,
+    pub fields: Vec<Field>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub public: bool,
+    pub mutable: bool,
+    pub ty: Type,
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Enum {
+    pub name: String,
+    pub public: bool,
+    pub derives: Vec<String>,
+    pub excluded_derives: Vec<String>,
+    pub variants: Vec<Variant>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub default: bool,
+    pub fields: Vec<Type>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trait {
+    pub name: String,
+    pub public: bool,
+    pub fields: Vec<Field>,
+    pub functions: Vec<Function>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Impl {
+    pub trait_name: Option<Path>,
+    pub self_ty: Type,
+    pub accessors: Vec<Field>,
+    pub functions: Vec<Function>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub public: bool,
+    pub self_param: Option<SelfParam>,
+    pub params: Vec<Param>,
+    pub block_param: Option<BlockParam>,
+    pub return_ty: Option<Type>,
+    pub body: Option<Block>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfParam {
+    pub borrow: bool,
+    pub mutable: bool,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub mutable: bool,
+    pub ty: Type,
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockParam {
+    pub name: String,
+    pub params: Vec<Type>,
+    pub return_ty: Option<Type>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Use {
+    pub public: bool,
+    pub path: String,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternCrate {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroRules {
+    pub name: String,
+    pub body: String,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Type {
+    pub name: String,
+    pub generics: Vec<Type>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    pub segments: Vec<String>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stmt {
+    Let(Let),
+    Expr(Expr),
+    Error(Span),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Let {
+    pub name: String,
+    pub mutable: bool,
+    pub ty: Option<Type>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
+    Literal(Literal),
+    Path(Path),
+    Call { callee: Box<Expr>, args: Vec<Arg> },
+    MethodCall { receiver: Box<Expr>, method: String, generics: Vec<Type>, args: Vec<Arg> },
+    Field { receiver: Box<Expr>, name: String },
+    Struct { path: Path, fields: Vec<(String, Expr)> },
+    Index { receiver: Box<Expr>, index: Box<Expr> },
+    Try(Box<Expr>),
+    Unary { op: UnaryOp, operand: Box<Expr> },
+    Binary { op: BinaryOp, left: Box<Expr>, right: Box<Expr> },
+    Assign { op: Option<BinaryOp>, target: Box<Expr>, value: Box<Expr> },
+    Paren(Box<Expr>),
+    Array(Vec<Expr>),
+    Tuple(Vec<Expr>),
+    HashMap(Vec<(Expr, Expr)>),
+    MacroCall(MacroCall),
+    If(If),
+    Match(Match),
+    For(For),
+    While(While),
+    Loop(Loop),
+    Closure(Closure),
+    Range { start: Option<Box<Expr>>, end: Option<Box<Expr>>, inclusive: bool },
+    Return(Option<Box<Expr>>),
+    Break { label: Option<String>, value: Option<Box<Expr>> },
+    Continue { label: Option<String> },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Integer(String),
+    Float(String),
+    String(String),
+    Char(String),
+    Boolean(bool),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Negate,
+    Borrow,
+    BorrowMut,
+    Own,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arg {
+    pub name: Option<String>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter {
+    Paren,
+    Bracket,
+    Brace,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroCall {
+    pub name: String,
+    pub delimiter: Delimiter,
+    pub args: MacroArgs,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MacroArgs {
+    Exprs(Vec<Expr>),
+    Tokens(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct If {
+    pub condition: Box<Expr>,
+    pub then_branch: Block,
+    pub else_branch: Option<ElseBranch>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElseBranch {
+    If(Box<If>),
+    Block(Block),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct For {
+    pub label: Option<String>,
+    pub pattern: Pattern,
+    pub iterable: Box<Expr>,
+    pub body: Block,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct While {
+    pub label: Option<String>,
+    pub condition: Box<Expr>,
+    pub body: Block,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loop {
+    pub label: Option<String>,
+    pub body: Block,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Closure {
+    pub capture_by_move: bool,
+    pub params: Vec<ClosureParam>,
+    pub return_ty: Option<Type>,
+    pub body: Block,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClosureParam {
+    pub name: String,
+    pub mutable: bool,
+    pub ty: Option<Type>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub scrutinee: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatternKind {
+    Wildcard,
+    Literal(Literal),
+    Path(Path),
+    Tuple(Vec<Pattern>),
+    TupleStruct(Path, Vec<Pattern>),
+}
+
+impl Block {
+    pub fn walk_mut<F: FnMut(&mut Expr)>(&mut self, visit: &mut F) {
+        for stmt in &mut self.stmts {
+            stmt.walk_mut(visit);
+        }
+    }
+}
+
+impl If {
+    pub fn walk_mut<F: FnMut(&mut Expr)>(&mut self, visit: &mut F) {
+        self.condition.walk_mut(visit);
+        self.then_branch.walk_mut(visit);
+        match &mut self.else_branch {
+            Some(ElseBranch::If(item)) => item.walk_mut(visit),
+            Some(ElseBranch::Block(block)) => block.walk_mut(visit),
+            None => {},
+        }
+    }
+}
+
+impl Stmt {
+    pub fn walk_mut<F: FnMut(&mut Expr)>(&mut self, visit: &mut F) {
+        match self {
+            Stmt::Let(item) => item.value.walk_mut(visit),
+            Stmt::Expr(expr) => expr.walk_mut(visit),
+            Stmt::Error(_) => {},
+        }
+    }
+}
+
+pub fn walk_lets<F: FnMut(&Let)>(stmts: &mut [Stmt], visit: &mut F) {
+    for stmt in stmts.iter() {
+        if let Stmt::Let(binding) = stmt {
+            visit(binding);
+        }
+    }
+    for stmt in stmts {
+        stmt.walk_mut(&mut |expr| {
+            for block in expr.blocks() {
+                for stmt in &block.stmts {
+                    if let Stmt::Let(binding) = stmt {
+                        visit(binding);
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Expr {
+        Expr { kind, span }
+    }
+
+    pub fn blocks(&self) -> Vec<&Block> {
+        match &self.kind {
+            ExprKind::If(item) => {
+                let mut blocks = vec![&item.then_branch];
+                let mut else_branch = &item.else_branch;
+                while let Some(branch) = else_branch {
+                    match branch {
+                        ElseBranch::If(item) => {
+                            blocks.push(&item.then_branch);
+                            else_branch = &item.else_branch;
+                        },
+                        ElseBranch::Block(block) => {
+                            blocks.push(block);
+                            else_branch = &None;
+                        },
+                    }
+                }
+                blocks
+            },
+            ExprKind::Match(item) => item.arms.iter().map(|arm| &arm.body).collect(),
+            ExprKind::For(item) => vec![&item.body],
+            ExprKind::While(item) => vec![&item.body],
+            ExprKind::Loop(item) => vec![&item.body],
+            ExprKind::Closure(item) => vec![&item.body],
+            _ => vec![],
+        }
+    }
+
+    pub fn walk_mut<F: FnMut(&mut Expr)>(&mut self, visit: &mut F) {
+        match &mut self.kind {
+            ExprKind::Literal(_) |
+            ExprKind::Path(_) |
+            ExprKind::Return(None) |
+            ExprKind::Break { value: None, .. } |
+            ExprKind::Continue { .. } => {},
+            ExprKind::Call { callee, args } => {
+                callee.walk_mut(visit);
+                for arg in args {
+                    arg.value.walk_mut(visit);
+                }
+            },
+            ExprKind::MethodCall { receiver, args, .. } => {
+                receiver.walk_mut(visit);
+                for arg in args {
+                    arg.value.walk_mut(visit);
+                }
+            },
+            ExprKind::Field { receiver, .. } => receiver.walk_mut(visit),
+            ExprKind::Struct { fields, .. } => {
+                for (_, value) in fields {
+                    value.walk_mut(visit);
+                }
+            },
+            ExprKind::Index { receiver, index } => {
+                receiver.walk_mut(visit);
+                index.walk_mut(visit);
+            },
+            ExprKind::Try(expr) |
+            ExprKind::Paren(expr) |
+            ExprKind::Return(Some(expr)) |
+            ExprKind::Break { value: Some(expr), .. } => expr.walk_mut(visit),
+            ExprKind::Unary { operand, .. } => operand.walk_mut(visit),
+            ExprKind::Binary { left, right, .. } => {
+                left.walk_mut(visit);
+                right.walk_mut(visit);
+            },
+            ExprKind::Assign { target, value, .. } => {
+                target.walk_mut(visit);
+                value.walk_mut(visit);
+            },
+            ExprKind::Array(exprs) | ExprKind::Tuple(exprs) => {
+                for expr in exprs {
+                    expr.walk_mut(visit);
+                }
+            },
+            ExprKind::HashMap(entries) => {
+                for (key, value) in entries {
+                    key.walk_mut(visit);
+                    value.walk_mut(visit);
+                }
+            },
+            ExprKind::MacroCall(call) => {
+                if let MacroArgs::Exprs(exprs) = &mut call.args {
+                    for expr in exprs {
+                        expr.walk_mut(visit);
+                    }
+                }
+            },
+            ExprKind::If(item) => item.walk_mut(visit),
+            ExprKind::For(item) => {
+                item.iterable.walk_mut(visit);
+                item.body.walk_mut(visit);
+            },
+            ExprKind::While(item) => {
+                item.condition.walk_mut(visit);
+                item.body.walk_mut(visit);
+            },
+            ExprKind::Loop(item) => item.body.walk_mut(visit),
+            ExprKind::Closure(item) => item.body.walk_mut(visit),
+            ExprKind::Range { start, end, .. } => {
+                for expr in start.iter_mut().chain(end.iter_mut()) {
+                    expr.walk_mut(visit);
+                }
+            },
+            ExprKind::Match(item) => {
+                item.scrutinee.walk_mut(visit);
+                for arm in &mut item.arms {
+                    if let Some(guard) = &mut arm.guard {
+                        guard.walk_mut(visit);
+                    }
+                    arm.body.walk_mut(visit);
+                }
+            },
+        }
+        visit(self);
+    }
+}
+
+impl Path {
+    pub fn name(&self) -> &str {
+        self.segments.last().map_or("", |segment| segment.as_str())
+    }
+}
@@ -0,0 +1,145 @@
+use crate::ast::{ Module, Item, Struct, Impl, Function, Param, Type, Path, Block, Stmt, Expr, ExprKind };
+use crate::diagnostic::Diagnostics;
+
+pub fn generate(module: &mut Module) -> Diagnostics {
+    generate_items(&mut module.items);
+    Diagnostics::new()
+}
+
+fn generate_items(items: &mut Vec<Item>) {
+    let mut index = 0;
+    while index < items.len() {
+        let generated = match &mut items[index] {
+            Item::Module(module) => {
+                generate_items(&mut module.items);
+                None
+            },
+            Item::Struct(item) if item.constructor => Some(constructor(item)),
+            _ => None,
+        };
+        if let Some(generated) = generated {
+            if !has_constructor(items, &generated.self_ty.name) {
+                items.insert(index + 1, Item::Impl(generated));
+                index += 1;
+            }
+        }
+        index += 1;
+    }
+}
+
+fn has_constructor(items: &[Item], name: &str) -> bool {
+    items.iter().any(|item| match item {
+        Item::Impl(item) => {
+            item.trait_name.is_none() &&
+                item.self_ty.name == name &&
+                item.functions.iter().any(|function| function.name == "new")
+        },
+        _ => false,
+    })
+}
+
+fn constructor(item: &Struct) -> Impl {
+    let span = item.span;
+    let ty = |name: &str| Type { name: name.to_string(), generics: vec![], span };
+
+    let params = item.fields
+        .iter()
+        .map(|field| Param {
+            name: field.name.clone(),
+            mutable: false,
+            ty: field.ty.clone(),
+            default: field.default.clone(),
+            span,
+        })
+        .collect();
+
+    let fields = item.fields
+        .iter()
+        .map(|field| {
+            let value = Path { segments: vec![field.name.clone()], span };
+            (field.name.clone(), Expr::new(ExprKind::Path(value), span))
+        })
+        .collect();
+    let path = Path { segments: vec!["Self".to_string()], span };
+    let value = Expr::new(ExprKind::Struct { path, fields }, span);
+    let body = Block { stmts: vec![Stmt::Expr(value)], span };
+
+    let function = Function {
+        name: "new".to_string(),
+        public: true,
+        self_param: None,
+        params,
+        block_param: None,
+        return_ty: Some(ty("Self")),
+        body: Some(body),
+        span,
+    };
+    Impl { trait_name: None, self_ty: ty(&item.name), accessors: vec![], functions: vec![function], span }
+}
+
+#[test]
+fn test_generate() {
+    use crate::parser;
+    use crate::source::FileId;
+    use crate::transpiler::transpile;
+
+    let source = "\
+struct Cat do
+  name as String
+  lives as Int32 = 9
+  playful as Boolean
+end
+
+no new
+struct Token do
+  text as String
+end
+
+struct Dog do
+  name as String
+end
+
+implements Dog do
+  function new() as Self do
+    return Dog::default()
+  end
+end
+
+struct Marker do
+end
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    assert!(generate(&mut module).is_empty());
+
+    let constructors = module.items
+        .iter()
+        .filter(|item| matches!(item, Item::Impl(_)))
+        .count();
+    assert_eq!(constructors, 3);
+    let rust = transpile(&module);
+    assert!(rust.contains("\
+#[derive(Debug, Clone, PartialEq)]
+struct Cat {
+    name: String,
+    lives: i32,
+    playful: bool,
+}
+
+impl Default for Cat {
+    fn default() -> Self {
+        Self {
+            name: Default::default(),
+            lives: 9,
+            playful: Default::default(),
+        }
+    }
+}
+
+impl Cat {
+    pub fn new(name: String, lives: i32, playful: bool) -> Self {
+        Self { name, lives, playful }
+    }
+}
+"));
+    assert!(rust.contains("    pub fn new() -> Self {\n        Self {}\n    }"));
+}
@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::source::SourceMap;
+use crate::token::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub expected: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: &str, span: Span) -> Diagnostic {
+        Diagnostic { severity, message: message.to_string(), span, expected: vec![], notes: vec![] }
+    }
+
+    pub fn error(message: &str, span: Span) -> Diagnostic {
+        Diagnostic::new(Severity::Error, message, span)
+    }
+
+    pub fn warning(message: &str, span: Span) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, message, span)
+    }
+
+    pub fn with_expected(mut self, expected: &str) -> Diagnostic {
+        self.expected.push(expected.to_string());
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn expected_label(&self) -> Option<String> {
+        match self.expected.split_last() {
+            None => None,
+            Some((last, [])) => Some(format!("expected {}", last)),
+            Some((last, rest)) => Some(format!("expected {} or {}", rest.join(", "), last)),
+        }
+    }
+
+    pub fn render(&self, source_map: &SourceMap) -> String {
+        let file = source_map.file(self.span.file);
+        let (line, column) = file.line_column(self.span.start);
+        let line_text = file.line_text(line);
+        let gutter = " ".repeat(line.to_string().len());
+
+        let padding = line_text
+            .chars()
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let underlined = file.span_text(self.span).split('\n').next().unwrap_or("").trim_end_matches('\r');
+        let carets = "^".repeat(underlined.chars().count().max(1));
+        let label = self.expected_label().map_or(String::new(), |label| format!(" {}", label));
+
+        let mut output = format!("{}: {}\n", self.severity, self.message);
+        output.push_str(&format!("{}--> {}:{}:{}\n", gutter, file.name, line, column));
+        output.push_str(&format!("{} |\n", gutter));
+        output.push_str(&format!("{} | {}\n", line, line_text));
+        output.push_str(&format!("{} | {}{}{}\n", gutter, padding, carets, label));
+        for note in &self.notes {
+            output.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+        output
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} at {}:{}", self.severity, self.message, self.span.line, self.span.column)?;
+        if let Some(label) = self.expected_label() {
+            write!(f, ", {}", label)?;
+        }
+        Ok(())
+    }
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn render(&self, source_map: &SourceMap) -> String {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.render(source_map))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Diagnostics {
+        Diagnostics { diagnostics: vec![diagnostic] }
+    }
+}
+
+impl std::iter::FromIterator<Diagnostic> for Diagnostics {
+    fn from_iter<I: IntoIterator<Item = Diagnostic>>(iter: I) -> Diagnostics {
+        Diagnostics { diagnostics: iter.into_iter().collect() }
+    }
+}
+
+impl Extend<Diagnostic> for Diagnostics {
+    fn extend<I: IntoIterator<Item = Diagnostic>>(&mut self, iter: I) {
+        self.diagnostics.extend(iter);
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.iter()
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, diagnostic) in self.diagnostics.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Diagnostic {}
+
+impl Error for Diagnostics {}
+
+#[test]
+fn test_render() {
+    use crate::source::FileId;
+
+    let mut source_map = SourceMap::new();
+    let file = source_map.add_file("cat.am", "struct Cat do\n  playful Boolean\nend\n");
+    let diagnostic = Diagnostic::error("unexpected `Boolean`", Span::new(file, 24, 31, 2, 11))
+        .with_expected("`as`")
+        .with_note("fields are declared as `name as Type`");
+
+    assert_eq!(
+        diagnostic.render(&source_map),
+        "\
+error: unexpected `Boolean`
+ --> cat.am:2:11
+  |
+2 |   playful Boolean
+  |           ^^^^^^^ expected `as`
+  = note: fields are declared as `name as Type`
+"
+    );
+    assert_eq!(
+        Diagnostic::error("unexpected end of file", Span::new(FileId(0), 0, 0, 1, 1))
+            .with_expected("`,`")
+            .with_expected("`)`")
+            .to_string(),
+        "error: unexpected end of file at 1:1, expected `,` or `)`"
+    );
+}
@@ -0,0 +1,107 @@
+use crate::ast::{ Module, Item, Function, Use, Expr, ExprKind };
+use crate::diagnostic::Diagnostics;
+use crate::token::Span;
+
+const HASH_MAP: &str = "std::collections::HashMap";
+
+pub fn insert_collections(module: &mut Module) -> Diagnostics {
+    insert_items(&mut module.items);
+    Diagnostics::new()
+}
+
+fn insert_items(items: &mut Vec<Item>) {
+    let mut literal: Option<Span> = None;
+    for item in items.iter_mut() {
+        let mut visit = |expr: &mut Expr| {
+            if matches!(expr.kind, ExprKind::HashMap(_)) {
+                literal.get_or_insert(expr.span);
+            }
+        };
+        match item {
+            Item::Module(module) => insert_items(&mut module.items),
+            Item::Struct(item) => {
+                for default in item.fields.iter_mut().filter_map(|field| field.default.as_mut()) {
+                    default.walk_mut(&mut visit);
+                }
+            },
+            Item::Function(function) => walk_function(function, &mut visit),
+            Item::Impl(item) => {
+                for function in &mut item.functions {
+                    walk_function(function, &mut visit);
+                }
+            },
+            Item::Trait(item) => {
+                for function in &mut item.functions {
+                    walk_function(function, &mut visit);
+                }
+            },
+            Item::Stmt(stmt) => stmt.walk_mut(&mut visit),
+            _ => {},
+        }
+    }
+
+    let imported = items.iter().any(|item| matches!(item, Item::Use(item) if imports_hash_map(&item.path)));
+    if let Some(span) = literal.filter(|_| !imported) {
+        items.insert(0, Item::Use(Use { public: false, path: HASH_MAP.to_string(), span }));
+    }
+}
+
+fn imports_hash_map(path: &str) -> bool {
+    let path = path.split_whitespace().collect::<String>();
+    match path.strip_prefix("std::collections::") {
+        Some("HashMap") | Some("*") => true,
+        Some(group) if group.starts_with('{') && group.ends_with('}') => {
+            group[1..group.len() - 1].split(',').any(|name| name == "HashMap" || name == "*")
+        },
+        _ => false,
+    }
+}
+
+fn walk_function<F: FnMut(&mut Expr)>(function: &mut Function, visit: &mut F) {
+    for default in function.params.iter_mut().filter_map(|param| param.default.as_mut()) {
+        default.walk_mut(visit);
+    }
+    if let Some(body) = &mut function.body {
+        body.walk_mut(visit);
+    }
+}
+
+#[test]
+fn test_insert_collections() {
+    use crate::parser;
+    use crate::source::FileId;
+    use crate::transpiler::transpile;
+
+    let source = "\
+module scores do
+  function defaults() do
+    let scores = {ada: 1}
+  end
+end
+
+module imported do
+  use std::collections::HashMap
+  let names = {1: \"one\"}
+end
+
+module plain do
+  let names = [1]
+end
+
+module extended do
+  use my::HashMapExt
+  let names = {1: \"one\"}
+end
+
+module grouped do
+  use std::collections::{ HashSet, HashMap }
+  let names = {1: \"one\"}
+end
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    assert!(insert_collections(&mut module).is_empty());
+    let rust = transpile(&module);
+    assert_eq!(rust.matches("use std::collections::HashMap;").count(), 3);
+    assert!(rust.contains("mod scores {\n    use std::collections::HashMap;\n"));
+    assert!(rust.contains("mod extended {\n    use std::collections::HashMap;\n    use my::HashMapExt;\n"));
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use crate::ast::{ Module, Item, Struct, Field, Path };
+use crate::diagnostic::{ Diagnostic, Diagnostics };
+use crate::resolve;
+
+type StructKey = Vec<String>;
+
+#[derive(Clone, Debug)]
+struct Flattened {
+    fields: Vec<Field>,
+    derives: Vec<String>,
+    excluded_derives: Vec<String>,
+}
+
+struct Inheritance {
+    structs: HashMap<StructKey, Struct>,
+    flattened: HashMap<StructKey, Option<Flattened>>,
+    visiting: Vec<StructKey>,
+    diagnostics: Diagnostics,
+}
+
+pub fn flatten(module: &mut Module) -> Diagnostics {
+    let mut inheritance = Inheritance {
+        structs: HashMap::new(),
+        flattened: HashMap::new(),
+        visiting: vec![],
+        diagnostics: Diagnostics::new(),
+    };
+    collect_structs(&module.items, &mut vec![], &mut inheritance.structs, &mut inheritance.diagnostics);
+
+    let mut keys = inheritance.structs.keys().cloned().collect::<Vec<_>>();
+    keys.sort_by_key(|key| inheritance.structs[key].span.start);
+    for key in &keys {
+        inheritance.flatten(key);
+    }
+
+    apply(&mut module.items, &mut vec![], &inheritance.flattened);
+    inheritance.diagnostics
+}
+
+fn collect_structs(
+    items: &[Item],
+    scope: &mut Vec<String>,
+    structs: &mut HashMap<StructKey, Struct>,
+    diagnostics: &mut Diagnostics,
+) {
+    for item in items {
+        match item {
+            Item::Struct(item) => {
+                let mut key = scope.clone();
+                key.push(item.name.clone());
+                match structs.entry(key) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(item.clone());
+                    },
+                    Entry::Occupied(_) => {
+                        let message = format!("struct `{}` is defined more than once", item.name);
+                        diagnostics.push(Diagnostic::error(&message, item.span));
+                    },
+                }
+            },
+            Item::Module(module) => {
+                scope.push(module.name.clone().unwrap_or_default());
+                collect_structs(&module.items, scope, structs, diagnostics);
+                scope.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+fn apply(items: &mut [Item], scope: &mut Vec<String>, flattened: &HashMap<StructKey, Option<Flattened>>) {
+    for item in items {
+        match item {
+            Item::Struct(item) => {
+                let mut key = scope.clone();
+                key.push(item.name.clone());
+                if let Some(Some(result)) = flattened.get(&key) {
+                    item.fields = result.fields.clone();
+                    item.derives = result.derives.clone();
+                    item.excluded_derives = result.excluded_derives.clone();
+                }
+            },
+            Item::Module(module) => {
+                scope.push(module.name.clone().unwrap_or_default());
+                apply(&mut module.items, scope, flattened);
+                scope.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+impl Inheritance {
+    fn flatten(&mut self, key: &StructKey) -> Option<Flattened> {
+        if let Some(result) = self.flattened.get(key) {
+            return result.clone()
+        }
+        let item = self.structs[key].clone();
+
+        if let Some(index) = self.visiting.iter().position(|visiting| visiting == key) {
+            let cycle = self.visiting[index..]
+                .iter()
+                .chain(std::iter::once(key))
+                .map(|key| key.last().map_or("", |name| name.as_str()))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let span = item.parent.as_ref().map_or(item.span, |parent| parent.span);
+            self.diagnostics.push(
+                Diagnostic::error(&format!("struct `{}` inherits from itself", item.name), span)
+                    .with_note(&format!("the inheritance cycle is {}", cycle))
+            );
+            return None
+        }
+
+        self.visiting.push(key.clone());
+        let result = match &item.parent {
+            None => Some(self.merge(&item, Flattened { fields: vec![], derives: vec![], excluded_derives: vec![] }, None)),
+            Some(parent) => match self.lookup(&key[..key.len() - 1], parent) {
+                Some(parent_key) => {
+                    self.flatten(&parent_key).map(|inherited| self.merge(&item, inherited, Some(parent.name())))
+                },
+                None => {
+                    let message = format!("cannot find parent struct `{}` for `{}`", parent.segments.join("::"), item.name);
+                    self.diagnostics.push(Diagnostic::error(&message, parent.span));
+                    None
+                },
+            },
+        };
+        self.visiting.pop();
+
+        self.flattened.insert(key.clone(), result.clone());
+        result
+    }
+
+    fn merge(&mut self, item: &Struct, inherited: Flattened, parent_name: Option<&str>) -> Flattened {
+        let inherited_count = inherited.fields.len();
+        let mut fields = inherited.fields;
+        for field in &item.fields {
+            match fields.iter().position(|existing| existing.name == field.name) {
+                Some(index) if index < inherited_count => {
+                    let message = format!(
+                        "field `{}` is already inherited from `{}`", field.name, parent_name.unwrap_or_default()
+                    );
+                    self.diagnostics.push(
+                        Diagnostic::error(&message, field.span)
+                            .with_note("inherited fields cannot be redeclared in a child struct")
+                    );
+                },
+                Some(_) => {
+                    let message = format!("field `{}` is declared more than once in `{}`", field.name, item.name);
+                    self.diagnostics.push(Diagnostic::error(&message, field.span));
+                },
+                None => fields.push(field.clone()),
+            }
+        }
+
+        let derives = merge_names(inherited.derives, &item.derives);
+        let excluded_derives = merge_names(inherited.excluded_derives, &item.excluded_derives);
+        Flattened { fields, derives, excluded_derives }
+    }
+
+    fn lookup(&self, scope: &[String], path: &Path) -> Option<StructKey> {
+        resolve::candidates(scope, &path.segments)
+            .into_iter()
+            .find(|candidate| self.structs.contains_key(candidate))
+    }
+}
+
+fn merge_names(mut names: Vec<String>, extra: &[String]) -> Vec<String> {
+    for name in extra {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+#[test]
+fn test_flatten() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+module zoo do
+  derive(Serialize)
+  public struct Animal do
+    public name as String
+  end
+
+  module cats do
+    derive(Hash)
+    struct Cat inherits super::Animal do
+      playful as Boolean
+    end
+  end
+end
+
+struct Lion inherits zoo::cats::Cat do
+  roar as String
+end
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    assert!(flatten(&mut module).is_empty());
+
+    let lion = match module.items.last() {
+        Some(Item::Struct(lion)) => lion,
+        item => panic!("expected a struct, found {:?}", item),
+    };
+    assert_eq!(lion.fields.iter().map(|field| field.name.as_str()).collect::<Vec<_>>(), ["name", "playful", "roar"]);
+    assert_eq!(lion.derives, ["Serialize", "Hash"]);
+    assert!(lion.fields[0].public);
+}
+
+#[test]
+fn test_flatten_errors() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+struct Cat inherits Animal do
+  name as String
+end
+
+struct Egg inherits Chicken do
+end
+
+struct Chicken inherits Egg do
+end
+
+struct Chicken do
+end
+
+struct Feline do
+end
+
+module zoo do
+  struct Lion inherits Feline do
+  end
+end
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    let messages = flatten(&mut module).iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>();
+    assert_eq!(messages, [
+        "struct `Chicken` is defined more than once",
+        "cannot find parent struct `Animal` for `Cat`",
+        "struct `Egg` inherits from itself",
+        "cannot find parent struct `Feline` for `Lion`",
+    ]);
+
+    let source = "\
+struct Animal do
+  name as String
+end
+
+struct Dog inherits Animal do
+  name as String
+  age as Int32
+  age as Int64
+end
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    let messages = flatten(&mut module).iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>();
+    assert_eq!(messages, [
+        "field `name` is already inherited from `Animal`",
+        "field `age` is declared more than once in `Dog`",
+    ]);
+}
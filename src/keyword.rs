@@ -1,8 +1,10 @@
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Keyword {
     If,
+    Unless,
     Then,
     Else,
+    Elsif,
     For,
     Do,
     End,
@@ -21,10 +23,15 @@ pub enum Keyword {
     Module,
     Derive,
     Match,
+    When,
     Enum,
     Use,
     ExternCrate,
     Return,
+    Break,
+    Next,
+    While,
+    Loop,
     PublicStruct,
     PublicTrait,
     PublicUse,
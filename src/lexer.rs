@@ -0,0 +1,402 @@
+use crate::keyword::Keyword;
+use crate::token::{ Token, TokenKind, LiteralKind, Operator, Span };
+use crate::lexeme::Lexeme;
+use crate::diagnostic::{ Diagnostic, Diagnostics };
+use crate::source::FileId;
+
+pub struct Lexer<'a> {
+    file: FileId,
+    source: &'a str,
+    position: usize,
+    line: usize,
+    column: usize,
+    diagnostics: Diagnostics,
+}
+
+pub fn tokenize(file: FileId, source: &str) -> (Vec<Token>, Diagnostics) {
+    Lexer::new(file, source).tokenize()
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(file: FileId, source: &'a str) -> Lexer<'a> {
+        Lexer { file, source, position: 0, line: 1, column: 1, diagnostics: Diagnostics::new() }
+    }
+
+    pub fn tokenize(mut self) -> (Vec<Token>, Diagnostics) {
+        let mut tokens = vec![];
+        while let Some(token) = self.next_token() {
+            tokens.push(token);
+        }
+        (tokens, self.diagnostics)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.eat_while(|c| c == ' ' || c == '\t' || c == '\r');
+        let start = Span::new(self.file, self.position, self.position, self.line, self.column);
+        let current = self.peek()?;
+
+        let kind = match current {
+            '\n' => {
+                self.bump();
+                TokenKind::NewLine
+            },
+            '/' if self.peek_nth(1) == Some('/') => {
+                self.eat_while(|c| c != '\n');
+                TokenKind::LineComment
+            },
+            '/' if self.peek_nth(1) == Some('*') => {
+                self.eat_block_comment(start);
+                TokenKind::BlockComment
+            },
+            '"' => {
+                self.eat_string(start);
+                TokenKind::Lexeme(Lexeme::String)
+            },
+            '\'' => self.eat_quote(),
+            c if c.is_ascii_digit() => self.eat_number(),
+            c if c.is_alphabetic() || c == '_' => self.eat_word(),
+            _ => self.eat_punctuation(start),
+        };
+
+        Some(Token::new(kind, Span { end: self.position, ..start }))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.source[self.position..].chars().nth(n)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let current = self.peek()?;
+        self.position += current.len_utf8();
+        if current == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(current)
+    }
+
+    fn advance(&mut self, bytes: usize) {
+        let target = self.position + bytes;
+        while self.position < target {
+            self.bump();
+        }
+    }
+
+    fn eat_while<F: Fn(char) -> bool>(&mut self, predicate: F) {
+        while let Some(current) = self.peek() {
+            if !predicate(current) {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn eat_block_comment(&mut self, start: Span) {
+        self.bump();
+        self.bump();
+        loop {
+            if self.rest().starts_with("*/") {
+                self.bump();
+                self.bump();
+                return
+            }
+            if self.bump().is_none() {
+                self.diagnostics.push(
+                    Diagnostic::error("unterminated block comment", Span { end: self.position, ..start })
+                        .with_note("block comments are closed with `*/`")
+                );
+                return
+            }
+        }
+    }
+
+    fn eat_string(&mut self, start: Span) {
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('"') => return,
+                Some('\\') => {
+                    self.bump();
+                },
+                Some(_) => {},
+                None => {
+                    self.diagnostics.push(
+                        Diagnostic::error("unterminated string", Span { end: self.position, ..start })
+                            .with_expected("`\"`")
+                    );
+                    return
+                },
+            }
+        }
+    }
+
+    fn eat_quote(&mut self) -> TokenKind {
+        self.bump();
+        if self.peek() == Some('\\') {
+            self.bump();
+            self.bump();
+            self.eat_while(|c| c != '\'' && c != '\n');
+            self.bump();
+            return TokenKind::Lexeme(Lexeme::Char)
+        }
+        if self.peek_nth(1) == Some('\'') {
+            self.bump();
+            self.bump();
+            return TokenKind::Lexeme(Lexeme::Char)
+        }
+        self.eat_while(|c| c.is_alphanumeric() || c == '_');
+        TokenKind::Lifetime
+    }
+
+    fn eat_number(&mut self) -> TokenKind {
+        self.eat_while(|c| c.is_ascii_digit() || c == '_');
+        let is_float =
+            self.peek() == Some('.') &&
+            self.peek_nth(1).is_some_and(|c| c.is_ascii_digit());
+        if is_float {
+            self.bump();
+            self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            TokenKind::Lexeme(Lexeme::Float64)
+        } else {
+            TokenKind::Lexeme(Lexeme::Int32)
+        }
+    }
+
+    fn eat_word(&mut self) -> TokenKind {
+        let start = self.position;
+        self.eat_while(|c| c.is_alphanumeric() || c == '_');
+        let word = &self.source[start..self.position];
+
+        if self.peek() == Some('!') && self.peek_nth(1) != Some('=') {
+            self.bump();
+            if word == "macro_rules" {
+                self.eat_while(|c| c == ' ' || c == '\t');
+                self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                return TokenKind::MacroRules
+            }
+            return TokenKind::Macro
+        }
+
+        let compound = match word {
+            "public" => &[
+                ("struct", Keyword::PublicStruct),
+                ("trait", Keyword::PublicTrait),
+                ("use", Keyword::PublicUse),
+                ("module", Keyword::PublicModule),
+                ("function", Keyword::PublicFunction),
+                ("enum", Keyword::PublicEnum),
+            ][..],
+            "extern" => &[("crate", Keyword::ExternCrate)][..],
+            _ => &[][..],
+        };
+        for (next_word, keyword) in compound {
+            if self.eat_next_word(next_word) {
+                return TokenKind::Keyword(*keyword)
+            }
+        }
+        if word == "not" && self.eat_next_word("equal") {
+            return TokenKind::NotEqual
+        }
+
+        if let Some(literal_kind) = literal_kind(word) {
+            if !self.rest().starts_with("::") {
+                return TokenKind::Literal(literal_kind)
+            }
+        }
+
+        match word {
+            "module" => TokenKind::Keyword(Keyword::Module),
+            "let" => TokenKind::Keyword(Keyword::Let),
+            "do" => TokenKind::Keyword(Keyword::Do),
+            "end" => TokenKind::Keyword(Keyword::End),
+            "trait" => TokenKind::Keyword(Keyword::Trait),
+            "match" => TokenKind::Keyword(Keyword::Match),
+            "enum" => TokenKind::Keyword(Keyword::Enum),
+            "use" => TokenKind::Keyword(Keyword::Use),
+            "struct" => TokenKind::Keyword(Keyword::Struct),
+            "function" => TokenKind::Keyword(Keyword::Function),
+            "public" => TokenKind::Keyword(Keyword::Public),
+            "implements" => TokenKind::Keyword(Keyword::Implements),
+            "inherits" => TokenKind::Keyword(Keyword::Inherits),
+            "if" => TokenKind::Keyword(Keyword::If),
+            "unless" => TokenKind::Keyword(Keyword::Unless),
+            "when" => TokenKind::Keyword(Keyword::When),
+            "then" => TokenKind::Keyword(Keyword::Then),
+            "else" => TokenKind::Keyword(Keyword::Else),
+            "elsif" => TokenKind::Keyword(Keyword::Elsif),
+            "for" => TokenKind::Keyword(Keyword::For),
+            "in" => TokenKind::Keyword(Keyword::In),
+            "as" => TokenKind::Keyword(Keyword::As),
+            "mutable" => TokenKind::Keyword(Keyword::Mutable),
+            "borrow" => TokenKind::Keyword(Keyword::Borrow),
+            "own" => TokenKind::Keyword(Keyword::Own),
+            "return" => TokenKind::Keyword(Keyword::Return),
+            "break" => TokenKind::Keyword(Keyword::Break),
+            "next" => TokenKind::Keyword(Keyword::Next),
+            "while" => TokenKind::Keyword(Keyword::While),
+            "loop" => TokenKind::Keyword(Keyword::Loop),
+            "equal" => TokenKind::Equal,
+            "not" => TokenKind::Not,
+            _ => TokenKind::Identifier,
+        }
+    }
+
+    fn eat_next_word(&mut self, expected: &str) -> bool {
+        let rest = self.rest();
+        let after_blanks = rest.trim_start_matches([' ', '\t']);
+        let blanks = rest.len() - after_blanks.len();
+        let is_next_word =
+            blanks > 0 &&
+            after_blanks.starts_with(expected) &&
+            !after_blanks[expected.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_');
+        if is_next_word {
+            self.advance(blanks + expected.len());
+        }
+        is_next_word
+    }
+
+    fn eat_punctuation(&mut self, start: Span) -> TokenKind {
+        for (symbol, kind) in PUNCTUATION {
+            if self.rest().starts_with(symbol) {
+                self.advance(symbol.len());
+                return kind.clone()
+            }
+        }
+        let unexpected = self.bump().unwrap_or_default();
+        let message = format!("unexpected character `{}`", unexpected);
+        self.diagnostics.push(Diagnostic::error(&message, Span { end: self.position, ..start }));
+        TokenKind::Unknown
+    }
+}
+
+const PUNCTUATION: &[(&str, TokenKind)] = &[
+    ("...", TokenKind::DotDotDot),
+    ("..=", TokenKind::DotDotEq),
+    ("<<=", TokenKind::ShlEq),
+    (">>=", TokenKind::ShrEq),
+    ("..", TokenKind::DotDot),
+    ("::", TokenKind::NamespaceSeparator),
+    ("=>", TokenKind::FatArrow),
+    ("==", TokenKind::EqEq),
+    ("!=", TokenKind::Ne),
+    ("<=", TokenKind::Le),
+    (">=", TokenKind::Ge),
+    ("<<", TokenKind::Shl),
+    (">>", TokenKind::Shr),
+    ("<-", TokenKind::LArrow),
+    ("->", TokenKind::RArrow),
+    ("|>", TokenKind::PipeLine),
+    ("||", TokenKind::OrOr),
+    ("|=", TokenKind::OrEq),
+    ("&&", TokenKind::AndAnd),
+    ("&=", TokenKind::AndEq),
+    ("+=", TokenKind::PlusEq),
+    ("-=", TokenKind::MinusEq),
+    ("*=", TokenKind::StarEq),
+    ("/=", TokenKind::SlashEq),
+    ("%=", TokenKind::PercentEq),
+    ("^=", TokenKind::CaretEq),
+    ("=", TokenKind::Assign),
+    (":", TokenKind::DoubleDot),
+    ("!", TokenKind::Not),
+    ("<", TokenKind::Lt),
+    (">", TokenKind::Gt),
+    ("|", TokenKind::Or),
+    ("&", TokenKind::And),
+    ("+", TokenKind::Operator(Operator::Add)),
+    ("-", TokenKind::Operator(Operator::Minus)),
+    ("*", TokenKind::Operator(Operator::Multiply)),
+    ("/", TokenKind::Operator(Operator::Divide)),
+    ("%", TokenKind::Operator(Operator::Mod)),
+    (",", TokenKind::Comma),
+    (".", TokenKind::Dot),
+    (";", TokenKind::Semi),
+    ("?", TokenKind::Question),
+    ("(", TokenKind::OpenParen),
+    (")", TokenKind::CloseParen),
+    ("[", TokenKind::OpenBracket),
+    ("]", TokenKind::CloseBracket),
+    ("{", TokenKind::OpenBrace),
+    ("}", TokenKind::CloseBrace),
+    ("@", TokenKind::At),
+    ("#", TokenKind::Pound),
+    ("~", TokenKind::Tilde),
+    ("$", TokenKind::Dollar),
+    ("^", TokenKind::Caret),
+];
+
+fn literal_kind(word: &str) -> Option<LiteralKind> {
+    match word {
+        "Boolean" => Some(LiteralKind::Boolean),
+        "String" => Some(LiteralKind::String),
+        "Int32" => Some(LiteralKind::Int32),
+        "Int64" => Some(LiteralKind::Int64),
+        "UInt32" => Some(LiteralKind::UInt32),
+        "UInt64" => Some(LiteralKind::UInt64),
+        "Float64" => Some(LiteralKind::Float64),
+        "Float32" => Some(LiteralKind::Float32),
+        "Usize" => Some(LiteralKind::Usize),
+        "Isize" => Some(LiteralKind::Isize),
+        "Char" => Some(LiteralKind::Char),
+        "Byte" => Some(LiteralKind::Byte),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_tokenize() {
+    let source = "public function area(side as Int32) do\n  return side * side\nend\n";
+    let (tokens, diagnostics) = tokenize(FileId(0), source);
+    assert!(diagnostics.is_empty());
+    let kinds = tokens.iter().map(|token| token.kind.clone()).collect::<Vec<_>>();
+
+    assert_eq!(kinds, vec![
+        TokenKind::Keyword(Keyword::PublicFunction),
+        TokenKind::Identifier,
+        TokenKind::OpenParen,
+        TokenKind::Identifier,
+        TokenKind::Keyword(Keyword::As),
+        TokenKind::Literal(LiteralKind::Int32),
+        TokenKind::CloseParen,
+        TokenKind::Keyword(Keyword::Do),
+        TokenKind::NewLine,
+        TokenKind::Keyword(Keyword::Return),
+        TokenKind::Identifier,
+        TokenKind::Operator(Operator::Multiply),
+        TokenKind::Identifier,
+        TokenKind::NewLine,
+        TokenKind::Keyword(Keyword::End),
+        TokenKind::NewLine,
+    ]);
+    assert_eq!(tokens[0].span, Span::new(FileId(0), 0, 15, 1, 1));
+    assert_eq!(tokens[0].span.text(source), "public function");
+
+    let last_side = tokens.iter().rev().find(|token| token.kind == TokenKind::Identifier).unwrap();
+    assert_eq!((last_side.span.line, last_side.span.column), (2, 17));
+    assert_eq!(last_side.span.len(), 4);
+    assert_eq!(last_side.span.text(source), "side");
+}
+
+#[test]
+fn test_tokenize_errors() {
+    assert_eq!(tokenize(FileId(0), "let name = \"Flur").1.len(), 1);
+    assert_eq!(tokenize(FileId(0), "/* never closed").1.len(), 1);
+
+    let (tokens, diagnostics) = tokenize(FileId(0), "let price = 5€ + 3¢");
+    let errors = diagnostics.iter().collect::<Vec<_>>();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].message, "unexpected character `€`");
+    assert_eq!((errors[0].span.start, errors[0].span.end, errors[0].span.line, errors[0].span.column), (13, 16, 1, 14));
+    assert_eq!(errors[1].message, "unexpected character `¢`");
+    assert_eq!(tokens.iter().filter(|token| token.kind == TokenKind::Unknown).count(), 2);
+}
@@ -0,0 +1,23 @@
+pub mod keyword;
+pub mod token;
+pub mod transpiler;
+pub mod printer;
+pub mod ast;
+pub mod parser;
+pub mod lexer;
+pub mod source;
+pub mod lexeme;
+pub mod block_keyword;
+pub mod diagnostic;
+pub mod session;
+pub mod inheritance;
+pub mod resolve;
+pub mod traits;
+pub mod constructors;
+pub mod arguments;
+pub mod variants;
+pub mod imports;
+
+pub use ast::Module;
+pub use diagnostic::{ Diagnostic, Diagnostics, Severity };
+pub use session::{ Options, Session };
@@ -1,39 +1,231 @@
-#[macro_use]
-extern crate regex;
-#[macro_use]
-extern crate lazy_static;
-
+use std::env;
 use std::fs;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, Read, Write};
+use std::process;
+
+use amelia::{ Options, Session };
+use amelia::session::STDIN_FILE_NAME;
+
+const USAGE: &str = "\
+Usage:
+    amelia transpile [<input.am>] [-o <output.rs>] [--indent-width <n>]
+    amelia check [<input.am>...]
+    amelia help
+    amelia version
+
+When no input is given, or the input is `-`, the source is read from stdin.
+When no output is given, or the output is `-`, the Rust code is written to stdout.";
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Transpile { input: Option<String>, output: Option<String>, indent_width: Option<usize> },
+    Check { inputs: Vec<String> },
+    Help,
+    Version,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let exit_code = match parse_args(&args) {
+        Ok(command) => run(command),
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            EXIT_USAGE
+        }
+    };
+
+    process::exit(exit_code);
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return Err("missing command".to_string()),
+    };
+
+    match command {
+        "transpile" => {
+            let mut input = None;
+            let mut output = None;
+            let mut indent_width = None;
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "-o" | "--output" => {
+                        match rest.next() {
+                            Some(path) => output = Some(path.clone()),
+                            None => return Err(format!("`{}` expects a file name", arg)),
+                        }
+                    },
+                    "--indent-width" => {
+                        match rest.next().map(|width| width.parse::<usize>()) {
+                            Some(Ok(width)) => indent_width = Some(width),
+                            _ => return Err(format!("`{}` expects a number", arg)),
+                        }
+                    },
+                    _ if arg.starts_with('-') && arg != "-" => {
+                        return Err(format!("unknown option `{}`", arg))
+                    },
+                    _ if input.is_some() => {
+                        return Err("`transpile` accepts a single input file".to_string())
+                    },
+                    _ => input = Some(arg.clone()),
+                }
+            }
+            Ok(Command::Transpile { input, output, indent_width })
+        },
+        "check" => {
+            if let Some(arg) = rest.iter().find(|arg| arg.starts_with('-') && *arg != "-") {
+                return Err(format!("unknown option `{}`", arg))
+            }
+            Ok(Command::Check { inputs: rest.to_vec() })
+        },
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        "version" | "-V" | "--version" => Ok(Command::Version),
+        _ => Err(format!("unknown command `{}`", command)),
+    }
+}
 
-mod keyword;
-mod token;
-mod transpiler;
-mod ast;
-mod parser;
-mod lexeme;
-mod block_keyword;
+fn run(command: Command) -> i32 {
+    match command {
+        Command::Transpile { input, output, indent_width } => {
+            let input_name = display_name(input.as_deref());
+            let contents = match read_source(input.as_deref()) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    eprintln!("error: could not read {}: {}", input_name, error);
+                    return EXIT_FAILURE
+                }
+            };
+
+            let mut options = Options::with_file_name(input_name);
+            if let Some(indent_width) = indent_width {
+                options = options.indent_width(indent_width);
+            }
+            let mut session = Session::new(options);
+            let rust = match session.compile(&contents) {
+                Ok(rust) => rust,
+                Err(diagnostics) => {
+                    eprint!("{}", diagnostics.render(session.source_map()));
+                    return EXIT_FAILURE
+                }
+            };
+
+            if let Err(error) = write_output(output.as_deref(), &rust) {
+                eprintln!("error: could not write {}: {}", display_name(output.as_deref()), error);
+                return EXIT_FAILURE
+            }
+            EXIT_SUCCESS
+        },
+        Command::Check { inputs } => {
+            let inputs = if inputs.is_empty() { vec!["-".to_string()] } else { inputs };
+            let mut exit_code = EXIT_SUCCESS;
+            let mut session = Session::default();
+            for input in inputs {
+                let input_name = display_name(Some(&input));
+                let contents = match read_source(Some(&input)) {
+                    Ok(contents) => contents,
+                    Err(error) => {
+                        eprintln!("error: could not read {}: {}", input_name, error);
+                        exit_code = EXIT_FAILURE;
+                        continue
+                    }
+                };
+
+                let file = session.add_file(input_name, &contents);
+                let result = session.parse_file(file)
+                    .and_then(|mut module| session.analyze(&mut module));
+                if let Err(diagnostics) = result {
+                    eprint!("{}", diagnostics.render(session.source_map()));
+                    exit_code = EXIT_FAILURE;
+                }
+            }
+            exit_code
+        },
+        Command::Help => {
+            println!("{}", USAGE);
+            EXIT_SUCCESS
+        },
+        Command::Version => {
+            println!("amelia {}", env!("CARGO_PKG_VERSION"));
+            EXIT_SUCCESS
+        },
+    }
+}
+
+fn display_name(path: Option<&str>) -> &str {
+    match path {
+        None | Some("-") => STDIN_FILE_NAME,
+        Some(path) => path,
+    }
+}
 
-use ast::Node;
-use transpiler::transpile;
-use token::{Token, TokenKind};
-use block_keyword::DO;
+fn read_source(path: Option<&str>) -> io::Result<String> {
+    match path {
+        None | Some("-") => {
+            let mut contents = String::new();
+            io::stdin().read_to_string(&mut contents)?;
+            Ok(contents)
+        },
+        Some(path) => fs::read_to_string(path),
+    }
+}
+
+fn write_output(path: Option<&str>, contents: &str) -> io::Result<()> {
+    match path {
+        None | Some("-") => io::stdout().write_all(contents.as_bytes()),
+        Some(path) => File::create(path)?.write_all(contents.as_bytes()),
+    }
+}
 
 #[test]
 fn test() {
     let contents = fs::read_to_string("examples/test1.am")
         .expect("Something went wrong reading the syntax file");
 
-    let mut tree = 
-        Node 
-            {
-                token: Token::new(TokenKind::Root, 0, false), 
-                children: vec![], 
-                data: None 
-            };
-    parser::complete_parse(&contents, &mut tree, 1, DO);
+    let mut session = Session::new(Options::with_file_name("examples/test1.am"));
+    let mut module = session.parse(&contents)
+        .expect("Something went wrong parsing the syntax file");
+    session.analyze(&mut module)
+        .expect("Something went wrong analyzing the syntax file");
     let mut file = File::create("examples/testrs")
         .expect("Someting went wrong creating the file");
-    file.write_all(transpile(tree).as_bytes());
-}
\ No newline at end of file
+    file.write_all(session.transpile(&module).as_bytes())
+        .expect("Someting went wrong writing the file");
+}
+
+#[test]
+fn test_parse_args() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(
+        parse_args(&args(&["transpile", "in.am", "-o", "out.rs"])),
+        Ok(Command::Transpile {
+            input: Some("in.am".to_string()),
+            output: Some("out.rs".to_string()),
+            indent_width: None
+        })
+    );
+    assert_eq!(
+        parse_args(&args(&["transpile"])),
+        Ok(Command::Transpile { input: None, output: None, indent_width: None })
+    );
+    assert_eq!(
+        parse_args(&args(&["transpile", "--indent-width", "2"])),
+        Ok(Command::Transpile { input: None, output: None, indent_width: Some(2) })
+    );
+    assert!(parse_args(&args(&["transpile", "--indent-width", "two"])).is_err());
+    assert_eq!(
+        parse_args(&args(&["check", "a.am", "b.am"])),
+        Ok(Command::Check { inputs: vec!["a.am".to_string(), "b.am".to_string()] })
+    );
+    assert!(parse_args(&args(&["transpile", "in.am", "-o"])).is_err());
+    assert!(parse_args(&args(&["compile"])).is_err());
+    assert!(parse_args(&[]).is_err());
+}
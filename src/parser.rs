@@ -1,713 +1,1680 @@
-use regex::{ Regex, Captures };
+use crate::ast::{
+    Module, Item, Struct, Field, Enum, Variant, Trait, Impl, Function, SelfParam, Param, Use,
+    ExternCrate, MacroRules, Type, Path, Block, Stmt, Let, Expr, ExprKind, Literal, UnaryOp,
+    BinaryOp, Arg, Delimiter, MacroCall, MacroArgs, If, ElseBranch, Match, MatchArm, For, While,
+    Loop, Closure, ClosureParam, BlockParam, Pattern, PatternKind, STRUCT_DERIVES, ENUM_DERIVES
+};
+use crate::block_keyword;
+use crate::diagnostic::{ Diagnostic, Diagnostics };
 use crate::keyword::Keyword;
-use crate::token::{ Token, TokenKind, LiteralKind, Operator, Collection };
-use crate::ast::Node;
 use crate::lexeme::Lexeme;
-use crate::block_keyword;
+use crate::lexer;
+use crate::source::FileId;
+use crate::token::{ Token, TokenKind, Operator, Span };
+
+pub fn parse(file: FileId, source: &str) -> Result<Module, Diagnostics> {
+    let (module, diagnostics) = parse_partial(file, source);
+    if diagnostics.has_errors() {
+        Err(diagnostics)
+    } else {
+        Ok(module)
+    }
+}
 
-pub fn complete_parse<'a>(syntax: &'a str, tree: &mut Node, line_number: i32, begin_mark: &str) -> 
-    Result<&'a str, String> 
-{
-    let mut full_code: &'a str = syntax;
-    let mut new_line_number = line_number;
-    let mut begin_group_scope = 0;
-    let mut end_group_scope = 0;
-    loop {
-        let mut result = parse_hash_map(full_code, new_line_number);
+pub fn parse_partial(file: FileId, source: &str) -> (Module, Diagnostics) {
+    let (tokens, lexer_diagnostics) = lexer::tokenize(file, source);
+    let mut parser = Parser::new(file, source, tokens);
+    let module = parser.parse_root();
+
+    let mut diagnostics = lexer_diagnostics.into_iter().chain(parser.diagnostics).collect::<Vec<_>>();
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    (module, diagnostics.into_iter().collect())
+}
+
+pub struct Parser<'a> {
+    file: FileId,
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+    last_span: Span,
+    labels: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
 
-        if result.is_none() {
-            result = parse_to_token(full_code, new_line_number);
+impl<'a> Parser<'a> {
+    pub fn new(file: FileId, source: &'a str, tokens: Vec<Token>) -> Parser<'a> {
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| {
+                !matches!(
+                    token.kind,
+                    TokenKind::LineComment | TokenKind::BlockComment | TokenKind::Unknown
+                )
+            })
+            .collect();
+        Parser {
+            file,
+            source,
+            tokens,
+            position: 0,
+            last_span: Span::new(file, 0, 0, 1, 1),
+            labels: vec![],
+            diagnostics: vec![],
         }
+    }
 
-        if result.is_none() {
-            result = parse_macro_rules(full_code, new_line_number);
+    pub fn parse_root(&mut self) -> Module {
+        let mut items = self.parse_items();
+        while let Some(token) = self.peek() {
+            let diagnostic = self.unexpected(token, &["an item or statement"]);
+            self.diagnostics.push(diagnostic);
+            self.bump();
+            items.extend(self.parse_items());
         }
+        let span = Span::new(self.file, 0, self.source.len(), 1, 1);
+        Module { name: None, public: false, items, span }
+    }
 
-        if result.is_none() {
-            result = parse_macro(full_code, new_line_number);
+    fn parse_items(&mut self) -> Vec<Item> {
+        let mut items = vec![];
+        loop {
+            self.skip_newlines();
+            let start = self.peek_span();
+            match self.peek_kind() {
+                None | Some(TokenKind::Keyword(Keyword::End)) => return items,
+                _ => match self.parse_item() {
+                    Ok(item) => items.push(item),
+                    Err(diagnostic) => items.push(Item::Error(self.recover(diagnostic, start))),
+                },
+            }
         }
+    }
+
+    fn parse_item(&mut self) -> Result<Item, Diagnostic> {
+        Ok(match self.peek_kind() {
+            Some(TokenKind::Keyword(Keyword::Module)) |
+            Some(TokenKind::Keyword(Keyword::PublicModule)) => Item::Module(self.parse_module()?),
+            Some(TokenKind::Keyword(Keyword::Struct)) |
+            Some(TokenKind::Keyword(Keyword::PublicStruct)) => Item::Struct(self.parse_struct()?),
+            Some(TokenKind::Keyword(Keyword::Enum)) |
+            Some(TokenKind::Keyword(Keyword::PublicEnum)) => Item::Enum(self.parse_enum()?),
+            Some(TokenKind::Keyword(Keyword::Trait)) |
+            Some(TokenKind::Keyword(Keyword::PublicTrait)) => Item::Trait(self.parse_trait()?),
+            Some(TokenKind::Keyword(Keyword::Implements)) => Item::Impl(self.parse_impl()?),
+            Some(TokenKind::Keyword(Keyword::Function)) |
+            Some(TokenKind::Keyword(Keyword::PublicFunction)) => Item::Function(self.parse_function()?),
+            Some(TokenKind::Keyword(Keyword::Use)) |
+            Some(TokenKind::Keyword(Keyword::PublicUse)) => Item::Use(self.parse_use()?),
+            Some(TokenKind::Keyword(Keyword::ExternCrate)) => Item::ExternCrate(self.parse_extern_crate()?),
+            Some(TokenKind::MacroRules) => Item::MacroRules(self.parse_macro_rules()?),
+            Some(TokenKind::Identifier) if self.at_annotation() => self.parse_annotated_item()?,
+            _ => Item::Stmt(self.parse_stmt()?),
+        })
+    }
+
+    fn at_annotation(&self) -> bool {
+        (self.check_identifier("derive") && self.peek_nth_kind(1) == Some(&TokenKind::OpenParen)) ||
+            (self.check_identifier("no") && self.peek_nth_kind(1) == Some(&TokenKind::Identifier))
+    }
 
-        if result.is_none() {
-            result = parse_function_call(full_code, new_line_number);
+    fn parse_annotated_item(&mut self) -> Result<Item, Diagnostic> {
+        let mut derives = vec![];
+        let mut excluded = vec![];
+        let mut no_constructor: Option<Span> = None;
+        while self.at_annotation() {
+            let start = self.peek_span();
+            let negated = self.check_identifier("no");
+            if negated {
+                self.bump();
+            }
+            if self.check_identifier("derive") && self.peek_nth_kind(1) == Some(&TokenKind::OpenParen) {
+                self.bump();
+                self.bump();
+                let paths = self.parse_comma_separated(TokenKind::CloseParen, |parser| {
+                    let path = parser.parse_path("a trait name")?;
+                    Ok((path.segments.join("::"), path.span))
+                })?;
+                if negated {
+                    excluded.extend(paths);
+                } else {
+                    derives.extend(paths.into_iter().map(|(name, _)| name));
+                }
+            } else {
+                let (name, span) = self.expect_identifier("an annotation")?;
+                if name != "new" {
+                    return Err(Diagnostic::error(&format!("unknown annotation `no {}`", name), span)
+                        .with_expected("`new`")
+                        .with_expected("`derive`"))
+                }
+                no_constructor = Some(self.span_from(start));
+            }
+            self.end_of_statement()?;
+            self.skip_newlines();
         }
 
-        if result.is_none() {
-            result = parse_borrow(full_code, new_line_number);
+        match self.peek_kind() {
+            Some(TokenKind::Keyword(Keyword::Struct)) | Some(TokenKind::Keyword(Keyword::PublicStruct)) => {
+                let mut item = self.parse_struct()?;
+                item.derives = derives;
+                item.excluded_derives = self.check_excluded_derives(excluded, STRUCT_DERIVES, "a struct");
+                item.constructor = no_constructor.is_none();
+                Ok(Item::Struct(item))
+            },
+            Some(TokenKind::Keyword(Keyword::Enum)) | Some(TokenKind::Keyword(Keyword::PublicEnum)) => {
+                if let Some(span) = no_constructor {
+                    return Err(Diagnostic::error("`no new` can only be applied to a struct", span))
+                }
+                let mut item = self.parse_enum()?;
+                item.derives = derives;
+                item.excluded_derives = self.check_excluded_derives(excluded, ENUM_DERIVES, "an enum");
+                Ok(Item::Enum(item))
+            },
+            _ => Err(self.unexpected_here(&["`struct`", "`enum`"])),
         }
+    }
 
-        if result.is_none() {
-            result = parse_own(full_code, new_line_number);
+    fn check_excluded_derives(&mut self, excluded: Vec<(String, Span)>, defaults: &[&str], kind: &str) -> Vec<String> {
+        let mut names = vec![];
+        for (name, span) in excluded {
+            if defaults.contains(&name.as_str()) {
+                names.push(name);
+            } else {
+                let message = format!("`{}` is not derived by default for {}", name, kind);
+                self.diagnostics.push(
+                    Diagnostic::error(&message, span)
+                        .with_note(&format!("the default derives are {}", defaults.join(", ")))
+                );
+            }
         }
+        names
+    }
 
-        if result.is_none() {
-            result = parse_namespace_separator(full_code, new_line_number);
+    fn parse_module(&mut self) -> Result<Module, Diagnostic> {
+        let keyword = self.bump();
+        let public = keyword.kind == TokenKind::Keyword(Keyword::PublicModule);
+        let (name, _) = self.expect_identifier("a module name")?;
+        self.expect(TokenKind::Keyword(Keyword::Do), "`do`")?;
+        let items = self.parse_items();
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(Module { name: Some(name), public, items, span })
+    }
+
+    fn parse_struct(&mut self) -> Result<Struct, Diagnostic> {
+        let keyword = self.bump();
+        let public = keyword.kind == TokenKind::Keyword(Keyword::PublicStruct);
+        let (name, _) = self.expect_identifier("a struct name")?;
+        let parent = if self.eat(&TokenKind::Keyword(Keyword::Inherits)) {
+            Some(self.parse_path("a parent struct name")?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Keyword(Keyword::Do), "`do`")?;
+
+        let mut fields = vec![];
+        loop {
+            self.skip_newlines();
+            if self.check(&TokenKind::Keyword(Keyword::End)) || self.peek().is_none() {
+                break;
+            }
+            let start = self.peek_span();
+            match self.parse_field() {
+                Ok(field) => fields.push(field),
+                Err(diagnostic) => {
+                    self.recover(diagnostic, start);
+                },
+            }
         }
-            
-        if result.is_none() {
-            result = parse_operator(full_code, new_line_number);
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        for field in fields.iter().filter(|field| field.mutable) {
+            let message = format!("struct field `{}` cannot be `mutable`", field.name);
+            self.diagnostics.push(
+                Diagnostic::error(&message, field.span)
+                    .with_note("fields are mutable through a `let mutable` binding or a `mutable borrow`")
+            );
         }
+        Ok(Struct { name, public, parent, derives: vec![], excluded_derives: vec![], constructor: true, fields, span })
+    }
 
-        if result.is_none() {
-            result = parse_type(full_code, new_line_number);
-        }
+    fn parse_field(&mut self) -> Result<Field, Diagnostic> {
+        let start = self.peek_span();
+        let public = self.eat(&TokenKind::Keyword(Keyword::Public));
+        let mutable = self.eat(&TokenKind::Keyword(Keyword::Mutable));
+        let (name, _) = self.expect_identifier("a field name")?;
+        self.expect(TokenKind::Keyword(Keyword::As), "`as`")?;
+        let ty = self.parse_type()?;
+        let default = if self.eat(&TokenKind::Assign) { Some(self.parse_expr()?) } else { None };
+        let span = self.span_from(start);
+        self.end_of_statement()?;
+        Ok(Field { name, public, mutable, ty, default, span })
+    }
 
-        if result.is_none() {
-            result = parse_as(full_code, new_line_number);
+    fn parse_enum(&mut self) -> Result<Enum, Diagnostic> {
+        let keyword = self.bump();
+        let public = keyword.kind == TokenKind::Keyword(Keyword::PublicEnum);
+        let (name, _) = self.expect_identifier("an enum name")?;
+        self.expect(TokenKind::Keyword(Keyword::Do), "`do`")?;
+
+        let mut variants = vec![];
+        loop {
+            self.skip_newlines();
+            if self.check(&TokenKind::Keyword(Keyword::End)) || self.peek().is_none() {
+                break;
+            }
+            let start = self.peek_span();
+            match self.parse_variant() {
+                Ok(variant) => variants.push(variant),
+                Err(diagnostic) => {
+                    self.recover(diagnostic, start);
+                },
+            }
         }
+        self.check_default_variant(&variants);
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(Enum { name, public, derives: vec![], excluded_derives: vec![], variants, span })
+    }
 
-        if result.is_none() {
-            result = parse_not_operator(full_code, new_line_number);
+    fn parse_variant(&mut self) -> Result<Variant, Diagnostic> {
+        let start = self.peek_span();
+        let default = self.check_identifier("default") && self.peek_nth_kind(1) == Some(&TokenKind::Identifier);
+        if default {
+            self.bump();
         }
+        let (name, _) = self.expect_identifier("a variant name")?;
+        let fields = if self.eat(&TokenKind::OpenParen) {
+            self.parse_comma_separated(TokenKind::CloseParen, Parser::parse_type)?
+        } else {
+            vec![]
+        };
+        let span = self.span_from(start);
+        self.end_of_statement()?;
+        Ok(Variant { name, default, fields, span })
+    }
 
-        if result.is_none() {
-            result = parse_derive(full_code, new_line_number);
+    fn check_default_variant(&mut self, variants: &[Variant]) {
+        let mut defaults = variants.iter().filter(|variant| variant.default);
+        let first = defaults.next();
+        for variant in defaults {
+            let message = format!("enum already has default variant `{}`", first.map_or("", |first| first.name.as_str()));
+            self.diagnostics.push(Diagnostic::error(&message, variant.span));
+        }
+        if let Some(variant) = first.filter(|variant| !variant.fields.is_empty()) {
+            let message = format!("default variant `{}` cannot have fields", variant.name);
+            self.diagnostics.push(
+                Diagnostic::error(&message, variant.span)
+                    .with_note("only unit variants can be marked `default`")
+            );
         }
+    }
 
-        if result.is_none() {
-            result = parse_type_with_generics(full_code, new_line_number);
+    fn parse_trait(&mut self) -> Result<Trait, Diagnostic> {
+        let keyword = self.bump();
+        let public = keyword.kind == TokenKind::Keyword(Keyword::PublicTrait);
+        let (name, _) = self.expect_identifier("a trait name")?;
+        self.expect(TokenKind::Keyword(Keyword::Do), "`do`")?;
+
+        let mut fields = vec![];
+        let mut functions = vec![];
+        loop {
+            self.skip_newlines();
+            let start = self.peek_span();
+            let result = match self.peek_kind() {
+                None | Some(TokenKind::Keyword(Keyword::End)) => break,
+                Some(TokenKind::Keyword(Keyword::Function)) |
+                Some(TokenKind::Keyword(Keyword::PublicFunction)) => {
+                    self.parse_function().map(|function| functions.push(function))
+                },
+                _ => self.parse_field().map(|field| fields.push(field)),
+            };
+            if let Err(diagnostic) = result {
+                self.recover(diagnostic, start);
+            }
         }
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(Trait { name, public, fields, functions, span })
+    }
 
-        if result.is_none() {
-            result = parse_string(full_code, new_line_number);
+    fn parse_impl(&mut self) -> Result<Impl, Diagnostic> {
+        let keyword = self.bump();
+        let first = self.parse_type()?;
+        let (trait_name, self_ty) = if self.eat(&TokenKind::Keyword(Keyword::For)) {
+            let segments = first.name.split("::").map(|segment| segment.to_string()).collect();
+            (Some(Path { segments, span: first.span }), self.parse_type()?)
+        } else {
+            (None, first)
+        };
+        self.expect(TokenKind::Keyword(Keyword::Do), "`do`")?;
+
+        let mut functions = vec![];
+        loop {
+            self.skip_newlines();
+            let start = self.peek_span();
+            let result = match self.peek_kind() {
+                None | Some(TokenKind::Keyword(Keyword::End)) => break,
+                Some(TokenKind::Keyword(Keyword::Function)) |
+                Some(TokenKind::Keyword(Keyword::PublicFunction)) => {
+                    self.parse_function().map(|function| functions.push(function))
+                },
+                _ => Err(self.unexpected_here(&["`function`", "`end`"])),
+            };
+            if let Err(diagnostic) = result {
+                self.recover(diagnostic, start);
+            }
         }
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(Impl { trait_name, self_ty, accessors: vec![], functions, span })
+    }
 
-        if result.is_none() {
-            result = parse_array(full_code, new_line_number);
+    fn parse_function(&mut self) -> Result<Function, Diagnostic> {
+        let keyword = self.bump();
+        let public = keyword.kind == TokenKind::Keyword(Keyword::PublicFunction);
+        let (name, _) = self.expect_identifier("a function name")?;
+        self.expect(TokenKind::OpenParen, "`(`")?;
+
+        let mut self_param = None;
+        let mut params = vec![];
+        let mut block_param: Option<BlockParam> = None;
+        loop {
+            self.skip_newlines();
+            if self.eat(&TokenKind::CloseParen) {
+                break;
+            }
+            let start = self.peek_span();
+            if self.eat(&TokenKind::And) {
+                let param = self.parse_block_param(start)?;
+                self.skip_newlines();
+                if !self.eat(&TokenKind::CloseParen) {
+                    let message = format!("block parameter `{}` must be the last parameter", param.name);
+                    return Err(Diagnostic::error(&message, param.span))
+                }
+                block_param = Some(param);
+                break;
+            }
+            let mutable = self.eat(&TokenKind::Keyword(Keyword::Mutable));
+            let borrow = self.eat(&TokenKind::Keyword(Keyword::Borrow));
+            if self.check_identifier("self") {
+                self.bump();
+                self_param = Some(SelfParam { borrow, mutable, span: self.span_from(start) });
+            } else {
+                let (param_name, _) = self.expect_identifier("a parameter name")?;
+                self.expect(TokenKind::Keyword(Keyword::As), "`as`")?;
+                let ty = self.parse_type()?;
+                let default = if self.eat(&TokenKind::Assign) { Some(self.parse_expr()?) } else { None };
+                params.push(Param { name: param_name, mutable, ty, default, span: self.span_from(start) });
+            }
+            self.skip_newlines();
+            if !self.eat(&TokenKind::Comma) {
+                if !self.check(&TokenKind::CloseParen) {
+                    return Err(self.unexpected_here(&["`,`", "`)`"]))
+                }
+                self.bump();
+                break;
+            }
         }
 
-        if result.is_none() {
-            result = parse_tuple(full_code, new_line_number);
+        self.check_default_params(&params);
+
+        let return_ty = if self.eat(&TokenKind::Keyword(Keyword::As)) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let body = if self.eat(&TokenKind::Keyword(Keyword::Do)) {
+            let body = self.parse_block(&[Keyword::End]);
+            self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+            Some(body)
+        } else {
+            None
+        };
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(Function { name, public, self_param, params, block_param, return_ty, body, span })
+    }
+
+    fn parse_block_param(&mut self, start: Span) -> Result<BlockParam, Diagnostic> {
+        let (name, _) = self.expect_identifier("a block parameter name")?;
+        let params = if self.eat(&TokenKind::OpenParen) {
+            self.parse_comma_separated(TokenKind::CloseParen, Parser::parse_type)?
+        } else {
+            vec![]
+        };
+        let return_ty = if self.eat(&TokenKind::Keyword(Keyword::As)) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        Ok(BlockParam { name, params, return_ty, span: self.span_from(start) })
+    }
+
+    fn check_default_params(&mut self, params: &[Param]) {
+        let mut defaulted: Option<&Param> = None;
+        for param in params {
+            match (&param.default, defaulted) {
+                (Some(_), None) => defaulted = Some(param),
+                (None, Some(previous)) => {
+                    let message = format!(
+                        "parameter `{}` without a default value follows defaulted parameter `{}`", param.name, previous.name
+                    );
+                    self.diagnostics.push(
+                        Diagnostic::error(&message, param.span)
+                            .with_note("parameters with default values must come last")
+                    );
+                },
+                _ => {},
+            }
         }
+    }
 
-        if result.is_none() {
-            result = parse_identifier(full_code, new_line_number);
+    fn parse_use(&mut self) -> Result<Use, Diagnostic> {
+        let keyword = self.bump();
+        let public = keyword.kind == TokenKind::Keyword(Keyword::PublicUse);
+        if self.at_end_of_statement() {
+            return Err(self.unexpected_here(&["a path"]))
+        }
+        let start = self.peek_span();
+        while !self.at_end_of_statement() {
+            self.bump();
         }
+        let path = self.source[start.start..self.last_span.end].to_string();
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(Use { public, path, span })
+    }
+
+    fn parse_extern_crate(&mut self) -> Result<ExternCrate, Diagnostic> {
+        let keyword = self.bump();
+        let (name, _) = self.expect_identifier("a crate name")?;
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(ExternCrate { name, span })
+    }
+
+    fn parse_macro_rules(&mut self) -> Result<MacroRules, Diagnostic> {
+        let keyword = self.bump();
+        let name = self.text(keyword.span)["macro_rules!".len()..].trim().to_string();
+        let body = self.parse_token_group()?;
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(MacroRules { name, body, span })
+    }
 
-        if result.is_none() {
-            result = parse_line_comment(full_code, new_line_number);
+    fn parse_block(&mut self, terminators: &[Keyword]) -> Block {
+        let start = self.peek_span();
+        let mut stmts = vec![];
+        loop {
+            self.skip_newlines();
+            let stmt_start = self.peek_span();
+            match self.peek_kind() {
+                None => break,
+                Some(TokenKind::Keyword(keyword)) if terminators.contains(keyword) => break,
+                _ => match self.parse_stmt() {
+                    Ok(stmt) => stmts.push(stmt),
+                    Err(diagnostic) => stmts.push(Stmt::Error(self.recover(diagnostic, stmt_start))),
+                },
+            }
         }
+        Block { stmts, span: self.span_from(start) }
+    }
 
-        if result.is_none() {
-            result = parse_open_parens(full_code, new_line_number);
+    fn parse_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        if self.check(&TokenKind::Keyword(Keyword::Let)) {
+            return Ok(Stmt::Let(self.parse_let()?))
+        }
+        let mut expr = self.parse_expr()?;
+        if let Some(op) = self.assignment_operator() {
+            self.bump();
+            self.skip_newlines();
+            let value = self.parse_expr()?;
+            let span = self.span_from(expr.span);
+            expr = Expr::new(ExprKind::Assign { op, target: Box::new(expr), value: Box::new(value) }, span);
         }
+        if self.at_guard() {
+            expr = self.parse_guard(expr)?;
+        }
+        self.end_of_statement()?;
+        Ok(Stmt::Expr(expr))
+    }
 
-        if result.is_none() {
-            result = parse_close_parens(full_code, new_line_number);
+    fn assignment_operator(&self) -> Option<Option<BinaryOp>> {
+        match self.peek_kind()? {
+            TokenKind::Assign => Some(None),
+            TokenKind::PlusEq => Some(Some(BinaryOp::Add)),
+            TokenKind::MinusEq => Some(Some(BinaryOp::Subtract)),
+            TokenKind::StarEq => Some(Some(BinaryOp::Multiply)),
+            TokenKind::SlashEq => Some(Some(BinaryOp::Divide)),
+            TokenKind::PercentEq => Some(Some(BinaryOp::Modulo)),
+            _ => None,
         }
+    }
 
-        if result.is_none() {
-            result = parse_dot(full_code, new_line_number);
+    fn parse_guard(&mut self, body: Expr) -> Result<Expr, Diagnostic> {
+        let keyword = self.bump();
+        let mut condition = self.parse_expr()?;
+        if keyword.kind == TokenKind::Keyword(Keyword::Unless) {
+            condition = negate(condition);
         }
+        let then_branch = Block { span: body.span, stmts: vec![Stmt::Expr(body.clone())] };
+        let kind = ExprKind::If(If { condition: Box::new(condition), then_branch, else_branch: None });
+        Ok(Expr::new(kind, self.span_from(body.span)))
+    }
 
-        if result.is_none() {
-            result = parse_comma(full_code, new_line_number);
+    fn parse_let(&mut self) -> Result<Let, Diagnostic> {
+        let keyword = self.bump();
+        let mutable = self.eat(&TokenKind::Keyword(Keyword::Mutable));
+        let (name, _) = self.expect_identifier("a variable name")?;
+        let ty = if self.eat(&TokenKind::Keyword(Keyword::As)) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Assign, "`=`")?;
+        self.skip_newlines();
+        let value = self.parse_expr()?;
+        let span = self.span_from(keyword.span);
+        self.end_of_statement()?;
+        Ok(Let { name, mutable, ty, value, span })
+    }
+
+    pub fn parse_expr(&mut self) -> Result<Expr, Diagnostic> {
+        let mut value = self.parse_range()?;
+        while self.eat_continued(&TokenKind::PipeLine) {
+            self.skip_newlines();
+            let start = value.span;
+            let bare = self.ends_pipeline_stage(1);
+            value = match self.peek_kind() {
+                Some(TokenKind::Keyword(Keyword::Borrow)) | Some(TokenKind::Keyword(Keyword::Own)) if bare => {
+                    let op = if self.bump().kind == TokenKind::Keyword(Keyword::Borrow) {
+                        UnaryOp::Borrow
+                    } else {
+                        UnaryOp::Own
+                    };
+                    let operand = Box::new(parenthesize(value, false));
+                    Expr::new(ExprKind::Unary { op, operand }, self.span_from(start))
+                },
+                Some(TokenKind::Keyword(Keyword::Mutable)) if self.ends_pipeline_stage(2) => {
+                    self.bump();
+                    self.expect(TokenKind::Keyword(Keyword::Borrow), "`borrow`")?;
+                    let operand = Box::new(parenthesize(value, false));
+                    Expr::new(ExprKind::Unary { op: UnaryOp::BorrowMut, operand }, self.span_from(start))
+                },
+                Some(TokenKind::Dot) => self.parse_postfix_operators(parenthesize(value, true))?,
+                _ => {
+                    let stage = self.parse_or()?;
+                    self.pipe_into(value, stage)?
+                },
+            };
         }
+        Ok(value)
+    }
 
-        if result.is_none() {
-            result = parse_block_comment(full_code, new_line_number);
-            if let Some(result_parsed) = &result {
-                let newlines = match_newlines((result_parsed.1).0);
-                new_line_number = new_line_number + newlines.len() as i32;
+    fn pipe_into(&mut self, value: Expr, stage: Expr) -> Result<Expr, Diagnostic> {
+        let start = value.span;
+        let argument = |value: Expr| Arg { name: None, span: value.span, value };
+        let kind = match stage.kind {
+            ExprKind::Path(_) => ExprKind::Call { callee: Box::new(stage), args: vec![argument(value)] },
+            ExprKind::Call { callee, mut args } => {
+                args.insert(0, argument(value));
+                ExprKind::Call { callee, args }
+            },
+            ExprKind::MethodCall { receiver, method, generics, mut args } => {
+                args.insert(0, argument(value));
+                ExprKind::MethodCall { receiver, method, generics, args }
+            },
+            ExprKind::MacroCall(MacroCall { name, delimiter, args: MacroArgs::Exprs(mut exprs) }) => {
+                exprs.push(value);
+                ExprKind::MacroCall(MacroCall { name, delimiter, args: MacroArgs::Exprs(exprs) })
+            },
+            _ => {
+                return Err(
+                    Diagnostic::error("cannot pipe a value into this expression", stage.span)
+                        .with_expected("a function")
+                        .with_expected("a method call")
+                        .with_expected("a macro call")
+                        .with_expected("`borrow`")
+                        .with_expected("`own`")
+                )
+            },
+        };
+        Ok(Expr::new(kind, self.span_from(start)))
+    }
+
+    fn ends_pipeline_stage(&self, offset: usize) -> bool {
+        matches!(
+            self.peek_nth_kind(offset),
+            None |
+            Some(TokenKind::NewLine) |
+            Some(TokenKind::Semi) |
+            Some(TokenKind::PipeLine) |
+            Some(TokenKind::CloseParen) |
+            Some(TokenKind::CloseBracket) |
+            Some(TokenKind::Comma) |
+            Some(TokenKind::Keyword(Keyword::End)) |
+            Some(TokenKind::Keyword(Keyword::If)) |
+            Some(TokenKind::Keyword(Keyword::Unless))
+        )
+    }
+
+    fn parse_range(&mut self) -> Result<Expr, Diagnostic> {
+        let start_span = self.peek_span();
+        let start = if self.check(&TokenKind::DotDot) || self.check(&TokenKind::DotDotEq) {
+            None
+        } else {
+            Some(self.parse_or()?)
+        };
+        let inclusive = match self.peek_kind() {
+            Some(TokenKind::DotDot) => false,
+            Some(TokenKind::DotDotEq) => true,
+            _ => return Ok(start.unwrap()),
+        };
+        self.bump();
+        let end = if self.at_range_end() {
+            if inclusive {
+                return Err(self.unexpected_here(&["the end of an inclusive range"]))
             }
+            None
+        } else {
+            Some(Box::new(self.parse_or()?))
+        };
+        let kind = ExprKind::Range { start: start.map(Box::new), end, inclusive };
+        Ok(Expr::new(kind, self.span_from(start_span)))
+    }
+
+    fn at_range_end(&self) -> bool {
+        matches!(
+            self.peek_kind(),
+            None |
+            Some(TokenKind::NewLine) |
+            Some(TokenKind::Semi) |
+            Some(TokenKind::Comma) |
+            Some(TokenKind::CloseParen) |
+            Some(TokenKind::CloseBracket) |
+            Some(TokenKind::PipeLine) |
+            Some(TokenKind::Keyword(Keyword::Do)) |
+            Some(TokenKind::Keyword(Keyword::Then)) |
+            Some(TokenKind::Keyword(Keyword::End))
+        )
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, Diagnostic> {
+        let mut left = self.parse_and()?;
+        while self.eat(&TokenKind::OrOr) {
+            left = self.binary(BinaryOp::Or, left, Parser::parse_and)?;
         }
+        Ok(left)
+    }
 
-        if result.is_none() {
-            result = parse_whitespace(full_code, new_line_number);
+    fn parse_and(&mut self) -> Result<Expr, Diagnostic> {
+        let mut left = self.parse_comparison()?;
+        while self.eat(&TokenKind::AndAnd) {
+            left = self.binary(BinaryOp::And, left, Parser::parse_comparison)?;
         }
+        Ok(left)
+    }
 
-        if result.is_none() {
-            result = parse_newline(full_code, new_line_number);
-            new_line_number = new_line_number + 1;
+    fn parse_comparison(&mut self) -> Result<Expr, Diagnostic> {
+        let mut left = self.parse_additive()?;
+        loop {
+            let op = match self.peek_kind() {
+                Some(TokenKind::EqEq) | Some(TokenKind::Equal) => BinaryOp::Equal,
+                Some(TokenKind::Ne) | Some(TokenKind::NotEqual) => BinaryOp::NotEqual,
+                Some(TokenKind::Lt) => BinaryOp::Less,
+                Some(TokenKind::Le) => BinaryOp::LessEqual,
+                Some(TokenKind::Gt) => BinaryOp::Greater,
+                Some(TokenKind::Ge) => BinaryOp::GreaterEqual,
+                _ => return Ok(left),
+            };
+            self.bump();
+            left = self.binary(op, left, Parser::parse_additive)?;
         }
+    }
 
-        if let Some(result_parsed) = result {
-            let token = result_parsed.0;
-            let mut tree_with_children =
-                Node {
-                    token,
-                    children: vec![],
-                    data: Some((result_parsed.1).0.to_string())
-                };
-            full_code = (result_parsed.1).1;
+    fn parse_additive(&mut self) -> Result<Expr, Diagnostic> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek_kind() {
+                Some(TokenKind::Operator(Operator::Add)) => BinaryOp::Add,
+                Some(TokenKind::Operator(Operator::Minus)) => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.bump();
+            left = self.binary(op, left, Parser::parse_multiplicative)?;
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, Diagnostic> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek_kind() {
+                Some(TokenKind::Operator(Operator::Multiply)) => BinaryOp::Multiply,
+                Some(TokenKind::Operator(Operator::Divide)) => BinaryOp::Divide,
+                Some(TokenKind::Operator(Operator::Mod)) => BinaryOp::Modulo,
+                _ => return Ok(left),
+            };
+            self.bump();
+            left = self.binary(op, left, Parser::parse_unary)?;
+        }
+    }
+
+    fn binary<F>(&mut self, op: BinaryOp, left: Expr, parse_right: F) -> Result<Expr, Diagnostic>
+        where F: Fn(&mut Parser<'a>) -> Result<Expr, Diagnostic>
+    {
+        self.skip_newlines();
+        let right = parse_right(self)?;
+        let span = left.span.merge(right.span);
+        Ok(Expr::new(ExprKind::Binary { op, left: Box::new(left), right: Box::new(right) }, span))
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, Diagnostic> {
+        let op = match self.peek_kind() {
+            Some(TokenKind::Not) => UnaryOp::Not,
+            Some(TokenKind::Operator(Operator::Minus)) => UnaryOp::Negate,
+            Some(TokenKind::Keyword(Keyword::Borrow)) => UnaryOp::Borrow,
+            Some(TokenKind::Keyword(Keyword::Own)) => UnaryOp::Own,
+            Some(TokenKind::Keyword(Keyword::Mutable)) => UnaryOp::BorrowMut,
+            _ => return self.parse_postfix(),
+        };
+        let start = self.bump().span;
+        if op == UnaryOp::BorrowMut {
+            self.expect(TokenKind::Keyword(Keyword::Borrow), "`borrow`")?;
+        }
+        let operand = self.parse_unary()?;
+        Ok(Expr::new(ExprKind::Unary { op, operand: Box::new(operand) }, self.span_from(start)))
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr, Diagnostic> {
+        let expr = self.parse_primary()?;
+        self.parse_postfix_operators(expr)
+    }
 
-            match tree_with_children.token.kind {
-                TokenKind::Macro | TokenKind::MacroRules =>  {
-                    full_code = parse_macro_body(full_code, &mut tree_with_children, line_number)?;
+    fn parse_postfix_operators(&mut self, mut expr: Expr) -> Result<Expr, Diagnostic> {
+        loop {
+            let start = expr.span;
+            let kind = match self.peek_kind() {
+                Some(TokenKind::OpenParen) => {
+                    self.bump();
+                    let args = self.parse_args()?;
+                    ExprKind::Call { callee: Box::new(expr), args }
                 },
-                _ => {
-                    if tree_with_children.token.kind == TokenKind::Keyword(Keyword::Function) || 
-                       tree_with_children.token.kind == TokenKind::Keyword(Keyword::PublicFunction) ||
-                       tree_with_children.token.kind == TokenKind::Keyword(Keyword::Struct) ||
-                       tree_with_children.token.kind == TokenKind::Keyword(Keyword::PublicStruct) {
-                            let result_code = complete_parse(full_code, &mut tree_with_children, line_number, block_keyword::DO);
-                            if let Ok(code) = result_code {
-                                full_code = code;
-                            }
+                Some(TokenKind::Dot) => {
+                    self.bump();
+                    let name = if self.check(&TokenKind::Lexeme(Lexeme::Int32)) {
+                        let token = self.bump();
+                        self.text(token.span).to_string()
+                    } else {
+                        self.expect_identifier("a field or method name")?.0
+                    };
+                    if self.eat(&TokenKind::NamespaceSeparator) {
+                        self.expect(TokenKind::Lt, "`<`")?;
+                        let generics = self.parse_generics()?;
+                        self.expect(TokenKind::OpenParen, "`(`")?;
+                        let args = self.parse_args()?;
+                        ExprKind::MethodCall { receiver: Box::new(expr), method: name, generics, args }
+                    } else if self.eat(&TokenKind::OpenParen) {
+                        let args = self.parse_args()?;
+                        ExprKind::MethodCall { receiver: Box::new(expr), method: name, generics: vec![], args }
+                    } else {
+                        ExprKind::Field { receiver: Box::new(expr), name }
                     }
+                },
+                Some(TokenKind::OpenBracket) => {
+                    self.bump();
+                    self.skip_newlines();
+                    let index = self.parse_expr()?;
+                    self.skip_newlines();
+                    self.expect(TokenKind::CloseBracket, "`]`")?;
+                    ExprKind::Index { receiver: Box::new(expr), index: Box::new(index) }
+                },
+                Some(TokenKind::Question) => {
+                    self.bump();
+                    ExprKind::Try(Box::new(expr))
+                },
+                Some(TokenKind::NewLine) if self.at_continued_line(&TokenKind::Dot) => {
+                    self.skip_newlines();
+                    continue
+                },
+                _ if self.at_block_argument() => {
+                    let block = self.parse_closure()?;
+                    pass_block(expr, block)?
+                },
+                _ => return Ok(expr),
+            };
+            expr = Expr::new(kind, self.span_from(start));
+        }
+    }
 
-                    if block_keyword::match_block_begin(&tree_with_children.token.kind, begin_mark) {
-                        begin_group_scope += 1;
-                    }
-                    if block_keyword::match_block_end(&tree_with_children.token.kind, begin_mark) {
-                        end_group_scope += 1;
-                    }
-                    if begin_group_scope == end_group_scope && begin_group_scope > 0 && end_group_scope > 0 {
-                        tree.children.push(tree_with_children);
-                        return Ok(full_code)
-                    }
-                }
+    fn at_closure(&self) -> bool {
+        let offset = if self.check_identifier("move") { 1 } else { 0 };
+        self.peek_nth_kind(offset) == Some(&TokenKind::Identifier) &&
+            self.text(self.tokens[self.position + offset].span) == "fn" &&
+            self.peek_nth_kind(offset + 1) == Some(&TokenKind::OpenParen)
+    }
+
+    fn at_block_argument(&self) -> bool {
+        let offset = if self.check_identifier("move") { 1 } else { 0 };
+        self.peek_nth_kind(offset) == Some(&TokenKind::Keyword(Keyword::Do)) &&
+            matches!(self.peek_nth_kind(offset + 1), Some(TokenKind::Or) | Some(TokenKind::OrOr))
+    }
+
+    fn parse_closure(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.peek_span();
+        let capture_by_move = self.check_identifier("move");
+        if capture_by_move {
+            self.bump();
+        }
+        let mut return_ty = None;
+        let params = if self.eat(&TokenKind::Keyword(Keyword::Do)) {
+            if self.eat(&TokenKind::OrOr) {
+                vec![]
+            } else {
+                self.expect(TokenKind::Or, "`|`")?;
+                self.parse_comma_separated(TokenKind::Or, Parser::parse_closure_param)?
             }
-            tree.children.push(tree_with_children);
         } else {
-            return Err(format!("pattern not recognize {}", syntax))
+            self.bump();
+            self.expect(TokenKind::OpenParen, "`(`")?;
+            let params = self.parse_comma_separated(TokenKind::CloseParen, Parser::parse_closure_param)?;
+            if self.eat(&TokenKind::Keyword(Keyword::As)) {
+                return_ty = Some(self.parse_type()?);
+            }
+            self.expect(TokenKind::Keyword(Keyword::Do), "`do`")?;
+            params
+        };
+        let body = self.parse_block(&[Keyword::End]);
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        let kind = ExprKind::Closure(Closure { capture_by_move, params, return_ty, body });
+        Ok(Expr::new(kind, self.span_from(start)))
+    }
+
+    fn parse_closure_param(&mut self) -> Result<ClosureParam, Diagnostic> {
+        let start = self.peek_span();
+        let mutable = self.eat(&TokenKind::Keyword(Keyword::Mutable));
+        let (name, _) = self.expect_identifier("a parameter name")?;
+        let ty = if self.eat(&TokenKind::Keyword(Keyword::As)) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        Ok(ClosureParam { name, mutable, ty, span: self.span_from(start) })
+    }
+
+    fn parse_args(&mut self) -> Result<Vec<Arg>, Diagnostic> {
+        self.parse_comma_separated(TokenKind::CloseParen, |parser| {
+            let start = parser.peek_span();
+            let named = parser.check(&TokenKind::Identifier) &&
+                        parser.peek_nth_kind(1) == Some(&TokenKind::DoubleDot);
+            let name = if named {
+                let (name, _) = parser.expect_identifier("an argument name")?;
+                parser.bump();
+                parser.skip_newlines();
+                Some(name)
+            } else {
+                None
+            };
+            let value = parser.parse_expr()?;
+            Ok(Arg { name, value, span: parser.span_from(start) })
+        })
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Diagnostic> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(self.unexpected_here(&["an expression"])),
+        };
+        match &token.kind {
+            TokenKind::Lexeme(lexeme) => {
+                self.bump();
+                let text = self.text(token.span).to_string();
+                let literal = match lexeme {
+                    Lexeme::Float64 | Lexeme::Float32 => Literal::Float(text),
+                    Lexeme::String => Literal::String(text),
+                    Lexeme::Char | Lexeme::Byte => Literal::Char(text),
+                    _ => Literal::Integer(text),
+                };
+                Ok(Expr::new(ExprKind::Literal(literal), token.span))
+            },
+            TokenKind::Identifier if self.check_identifier("true") || self.check_identifier("false") => {
+                self.bump();
+                let literal = Literal::Boolean(self.text(token.span) == "true");
+                Ok(Expr::new(ExprKind::Literal(literal), token.span))
+            },
+            TokenKind::Identifier if self.at_loop_label() => {
+                self.bump();
+                self.bump();
+                let label = self.text(token.span).to_string();
+                self.parse_loop(Some(label), token.span)
+            },
+            TokenKind::Identifier if self.at_closure() => self.parse_closure(),
+            TokenKind::Identifier | TokenKind::Literal(_) => {
+                let path = self.parse_path("an expression")?;
+                let span = path.span;
+                Ok(Expr::new(ExprKind::Path(path), span))
+            },
+            TokenKind::Macro => self.parse_macro_call(),
+            TokenKind::OpenParen => {
+                self.bump();
+                let (mut items, trailing_comma) = self.parse_comma_list(TokenKind::CloseParen, Parser::parse_expr)?;
+                let span = self.span_from(token.span);
+                if items.len() == 1 && !trailing_comma {
+                    Ok(Expr::new(ExprKind::Paren(Box::new(items.remove(0))), span))
+                } else {
+                    Ok(Expr::new(ExprKind::Tuple(items), span))
+                }
+            },
+            TokenKind::OpenBracket => {
+                self.bump();
+                let items = self.parse_comma_separated(TokenKind::CloseBracket, Parser::parse_expr)?;
+                Ok(Expr::new(ExprKind::Array(items), self.span_from(token.span)))
+            },
+            TokenKind::OpenBrace => {
+                self.bump();
+                let entries = self.parse_comma_separated(TokenKind::CloseBrace, Parser::parse_hash_map_entry)?;
+                Ok(Expr::new(ExprKind::HashMap(entries), self.span_from(token.span)))
+            },
+            TokenKind::Keyword(Keyword::If) => self.parse_if(),
+            TokenKind::Keyword(Keyword::Match) => self.parse_match(),
+            TokenKind::Keyword(Keyword::For) |
+            TokenKind::Keyword(Keyword::While) |
+            TokenKind::Keyword(Keyword::Loop) => self.parse_loop(None, token.span),
+            TokenKind::Keyword(Keyword::Unless) => self.parse_if(),
+            TokenKind::Keyword(Keyword::Return) => {
+                self.bump();
+                let value = self.parse_jump_value()?;
+                Ok(Expr::new(ExprKind::Return(value), self.span_from(token.span)))
+            },
+            TokenKind::Keyword(Keyword::Break) => {
+                self.bump();
+                let label = self.parse_jump_label();
+                let value = self.parse_jump_value()?;
+                Ok(Expr::new(ExprKind::Break { label, value }, self.span_from(token.span)))
+            },
+            TokenKind::Keyword(Keyword::Next) => {
+                self.bump();
+                let label = self.parse_jump_label();
+                Ok(Expr::new(ExprKind::Continue { label }, self.span_from(token.span)))
+            },
+            _ => Err(self.unexpected(&token, &["an expression"])),
         }
+    }
 
-        if full_code == "" {
-            return Ok(full_code)
+    fn parse_macro_call(&mut self) -> Result<Expr, Diagnostic> {
+        let token = self.bump();
+        let name = self.text(token.span).trim_end_matches('!').to_string();
+        let delimiter = match self.peek_kind() {
+            Some(TokenKind::OpenParen) => Delimiter::Paren,
+            Some(TokenKind::OpenBracket) => Delimiter::Bracket,
+            Some(TokenKind::OpenBrace) => Delimiter::Brace,
+            _ => return Err(self.unexpected_here(&["a macro body"])),
+        };
+
+        let open_position = self.position;
+        let diagnostic_count = self.diagnostics.len();
+        let open = self.bump();
+        let close_kind = block_keyword::end_token_kind(&open.kind).unwrap_or(TokenKind::Unknown);
+        let args = match self.parse_comma_separated(close_kind, Parser::parse_expr) {
+            Ok(exprs) => MacroArgs::Exprs(exprs),
+            Err(_) => {
+                self.position = open_position;
+                self.diagnostics.truncate(diagnostic_count);
+                MacroArgs::Tokens(self.parse_token_group()?)
+            },
+        };
+        let call = MacroCall { name, delimiter, args };
+        Ok(Expr::new(ExprKind::MacroCall(call), self.span_from(token.span)))
+    }
+
+    fn parse_hash_map_entry(&mut self) -> Result<(Expr, Expr), Diagnostic> {
+        let mut key = self.parse_expr()?;
+        if !self.eat(&TokenKind::FatArrow) {
+            if !self.eat(&TokenKind::DoubleDot) {
+                return Err(self.unexpected_here(&["`:`", "`=>`"]))
+            }
+            if let ExprKind::Path(path) = &key.kind {
+                if path.segments.len() == 1 {
+                    let symbol = Literal::String(format!("\"{}\"", path.name()));
+                    key = Expr::new(ExprKind::Literal(symbol), key.span);
+                }
+            }
         }
+        self.skip_newlines();
+        let value = self.parse_expr()?;
+        Ok((key, value))
     }
-}
 
-macro_rules! parse_capture {
-    ($syntax:expr, $RE:tt, $token_kind:tt, $line_number:expr, $eos:tt) => {
-        if let Some(caps) = $RE.captures($syntax) {
-            Some((
-                Token::new($token_kind, $line_number, $eos),
-                (
-                    caps.get(1).map_or("", |m| m.as_str()),
-                    caps.get(2).map_or("", |m| m.as_str()),
-                )
-            ))
+    fn parse_if(&mut self) -> Result<Expr, Diagnostic> {
+        let keyword = self.bump();
+        let if_node = self.parse_if_branches(keyword.kind == TokenKind::Keyword(Keyword::Unless))?;
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        Ok(Expr::new(ExprKind::If(if_node), self.span_from(keyword.span)))
+    }
+
+    fn parse_if_branches(&mut self, negated: bool) -> Result<If, Diagnostic> {
+        let mut condition = self.parse_expr()?;
+        if negated {
+            condition = negate(condition);
+        }
+        if !self.eat(&TokenKind::Keyword(Keyword::Then)) && !self.eat(&TokenKind::Keyword(Keyword::Do)) {
+            let diagnostic = self.unexpected_here(&["`then`", "`do`"]);
+            if self.peek_kind() != Some(&TokenKind::NewLine) {
+                return Err(diagnostic)
+            }
+            self.diagnostics.push(diagnostic);
+        }
+        let then_branch = self.parse_block(&[Keyword::Elsif, Keyword::Else, Keyword::End]);
+        let else_branch = if self.eat(&TokenKind::Keyword(Keyword::Elsif)) {
+            Some(ElseBranch::If(Box::new(self.parse_if_branches(false)?)))
+        } else if self.eat(&TokenKind::Keyword(Keyword::Else)) {
+            self.eat(&TokenKind::Keyword(Keyword::Do));
+            Some(ElseBranch::Block(self.parse_block(&[Keyword::End])))
         } else {
             None
-        }
+        };
+        Ok(If { condition: Box::new(condition), then_branch, else_branch })
     }
-}
 
-fn parse_open_parens<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(\\()(?s)(.*)$").unwrap();
+    fn at_loop_label(&self) -> bool {
+        self.peek_nth_kind(1) == Some(&TokenKind::DoubleDot) &&
+            matches!(
+                self.peek_nth_kind(2),
+                Some(TokenKind::Keyword(Keyword::For)) |
+                Some(TokenKind::Keyword(Keyword::While)) |
+                Some(TokenKind::Keyword(Keyword::Loop))
+            )
     }
-    let token_kind = TokenKind::OpenParen;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_close_parens<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(\\)\\n?)(?s)(.*)$").unwrap();
+    fn parse_loop(&mut self, label: Option<String>, start: Span) -> Result<Expr, Diagnostic> {
+        let keyword = self.bump();
+        let kind = match keyword.kind {
+            TokenKind::Keyword(Keyword::For) => {
+                let pattern = self.parse_pattern()?;
+                self.expect(TokenKind::Keyword(Keyword::In), "`in`")?;
+                let iterable = Box::new(self.parse_expr()?);
+                let body = self.parse_loop_body(&label)?;
+                ExprKind::For(For { label, pattern, iterable, body })
+            },
+            TokenKind::Keyword(Keyword::While) => {
+                let condition = Box::new(self.parse_expr()?);
+                let body = self.parse_loop_body(&label)?;
+                ExprKind::While(While { label, condition, body })
+            },
+            _ => {
+                let body = self.parse_loop_body(&label)?;
+                ExprKind::Loop(Loop { label, body })
+            },
+        };
+        Ok(Expr::new(kind, self.span_from(start)))
     }
-    let token_kind = TokenKind::CloseParen;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_dot<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(\\.)(?s)(.*)$").unwrap();
+    fn parse_loop_body(&mut self, label: &Option<String>) -> Result<Block, Diagnostic> {
+        self.expect(TokenKind::Keyword(Keyword::Do), "`do`")?;
+        self.labels.extend(label.clone());
+        let body = self.parse_block(&[Keyword::End]);
+        if label.is_some() {
+            self.labels.pop();
+        }
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        Ok(body)
     }
-    let token_kind = TokenKind::Dot;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_comma<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(,)(?s)(.*)$").unwrap();
+    fn parse_jump_label(&mut self) -> Option<String> {
+        let token = self.peek()?.clone();
+        let text = self.text(token.span);
+        if token.kind == TokenKind::Identifier && self.labels.iter().any(|label| label == text) {
+            self.bump();
+            Some(text.to_string())
+        } else {
+            None
+        }
     }
-    let token_kind = TokenKind::Comma;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_line_comment<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(//.*)(?s)(.*)$").unwrap();
+    fn parse_jump_value(&mut self) -> Result<Option<Box<Expr>>, Diagnostic> {
+        if self.at_end_of_statement() || self.at_guard() {
+            Ok(None)
+        } else {
+            Ok(Some(Box::new(self.parse_expr()?)))
+        }
     }
-    let token_kind = TokenKind::LineComment;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_block_comment<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(?s)(/\\*.*\\*/)(.*)$").unwrap();
+    fn parse_match(&mut self) -> Result<Expr, Diagnostic> {
+        let keyword = self.bump();
+        let scrutinee = self.parse_expr()?;
+        self.expect(TokenKind::Keyword(Keyword::Do), "`do`")?;
+
+        let mut arms = vec![];
+        loop {
+            self.skip_newlines();
+            if self.check(&TokenKind::Keyword(Keyword::End)) || self.peek().is_none() {
+                break;
+            }
+            let start = self.peek_span();
+            match self.parse_match_arm() {
+                Ok(arm) => arms.push(arm),
+                Err(diagnostic) => {
+                    self.recover(diagnostic, start);
+                },
+            }
+        }
+        self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+        let kind = ExprKind::Match(Match { scrutinee: Box::new(scrutinee), arms });
+        Ok(Expr::new(kind, self.span_from(keyword.span)))
     }
-    let token_kind = TokenKind::BlockComment;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn match_newlines<'a>(syntax: &'a str) -> Vec<Captures> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"\n").unwrap();
+    fn parse_match_arm(&mut self) -> Result<MatchArm, Diagnostic> {
+        let pattern = self.parse_pattern()?;
+        let guard = if self.eat(&TokenKind::Keyword(Keyword::When)) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::FatArrow, "`=>`")?;
+        self.skip_newlines();
+        let body = if self.eat(&TokenKind::Keyword(Keyword::Do)) {
+            let body = self.parse_block(&[Keyword::End]);
+            self.expect(TokenKind::Keyword(Keyword::End), "`end`")?;
+            body
+        } else {
+            let expr = self.parse_expr()?;
+            let span = expr.span;
+            Block { stmts: vec![Stmt::Expr(expr)], span }
+        };
+        let span = self.span_from(pattern.span);
+        self.end_of_statement()?;
+        Ok(MatchArm { pattern, guard, body, span })
     }
-    RE.captures_iter(syntax).collect()
-}
 
-fn parse_identifier<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^([A-Za-z_0-9]+\\n?)(?s)(\\s|\\(|.*)$").unwrap();
+    fn parse_pattern(&mut self) -> Result<Pattern, Diagnostic> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(self.unexpected_here(&["a pattern"])),
+        };
+        let kind = match &token.kind {
+            TokenKind::Identifier if self.check_identifier("_") => {
+                self.bump();
+                PatternKind::Wildcard
+            },
+            TokenKind::Identifier if self.check_identifier("true") || self.check_identifier("false") => {
+                self.bump();
+                PatternKind::Literal(Literal::Boolean(self.text(token.span) == "true"))
+            },
+            TokenKind::OpenParen => {
+                self.bump();
+                PatternKind::Tuple(self.parse_comma_separated(TokenKind::CloseParen, Parser::parse_pattern)?)
+            },
+            TokenKind::Lexeme(_) => match self.parse_primary()?.kind {
+                ExprKind::Literal(literal) => PatternKind::Literal(literal),
+                _ => return Err(self.unexpected(&token, &["a pattern"])),
+            },
+            TokenKind::Identifier | TokenKind::Literal(_) => {
+                let path = self.parse_path("a pattern")?;
+                if self.eat(&TokenKind::OpenParen) {
+                    let fields = self.parse_comma_separated(TokenKind::CloseParen, Parser::parse_pattern)?;
+                    PatternKind::TupleStruct(path, fields)
+                } else {
+                    PatternKind::Path(path)
+                }
+            },
+            _ => return Err(self.unexpected(&token, &["a pattern"])),
+        };
+        Ok(Pattern { kind, span: self.span_from(token.span) })
     }
-    let token_kind = TokenKind::Identifier;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_whitespace<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^([[:blank:]]+)(?s)(.*)$").unwrap();
+    fn parse_path(&mut self, expected: &str) -> Result<Path, Diagnostic> {
+        let start = self.peek_span();
+        let mut segments = vec![self.expect_path_segment(expected)?];
+        while self.eat(&TokenKind::NamespaceSeparator) {
+            segments.push(self.expect_path_segment("a path segment")?);
+        }
+        Ok(Path { segments, span: self.span_from(start) })
     }
-    let token_kind = TokenKind::Whitespace;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_newline<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(\\n)(?s)(.*)$").unwrap();
+    fn expect_path_segment(&mut self, expected: &str) -> Result<String, Diagnostic> {
+        match self.peek_kind() {
+            Some(TokenKind::Identifier) | Some(TokenKind::Literal(_)) => {
+                let token = self.bump();
+                Ok(self.text(token.span).to_string())
+            },
+            _ => Err(self.unexpected_here(&[expected])),
+        }
     }
-    let token_kind = TokenKind::NewLine;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_as<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(\\sas)(?s)(.*)$").unwrap();
+    fn parse_type(&mut self) -> Result<Type, Diagnostic> {
+        let start = self.peek_span();
+        let path = self.parse_path("a type")?;
+        let generics = if self.eat(&TokenKind::Lt) { self.parse_generics()? } else { vec![] };
+        Ok(Type { name: path.segments.join("::"), generics, span: self.span_from(start) })
     }
-    let token_kind = TokenKind::Keyword(Keyword::As);
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_type_with_generics<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^([A-Za-z0-9]+<.*>\\n?)(?s)(.*)$").unwrap();
+    fn parse_generics(&mut self) -> Result<Vec<Type>, Diagnostic> {
+        let mut generics = vec![];
+        loop {
+            generics.push(self.parse_type()?);
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect_closing_angle()?;
+        Ok(generics)
     }
-    let token_kind = TokenKind::TypeWithGeneric;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_macro<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^([A-Za-z_0-9]+!)(?s)(.*)$").unwrap();
+    fn expect_closing_angle(&mut self) -> Result<(), Diagnostic> {
+        if self.check(&TokenKind::Shr) {
+            let token = &mut self.tokens[self.position];
+            token.kind = TokenKind::Gt;
+            token.span.start += 1;
+            token.span.column += 1;
+            return Ok(())
+        }
+        self.expect(TokenKind::Gt, "`>`")?;
+        Ok(())
     }
-    let token_kind = TokenKind::Macro;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_macro_rules<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(macro_rules!\\s[A-Za-z_0-9]+)(?s)(.*)$").unwrap();
+    fn parse_comma_separated<T, F>(&mut self, close: TokenKind, parse_item: F) -> Result<Vec<T>, Diagnostic>
+        where F: Fn(&mut Parser<'a>) -> Result<T, Diagnostic>
+    {
+        self.parse_comma_list(close, parse_item).map(|(items, _)| items)
     }
-    let token_kind = TokenKind::MacroRules;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_namespace_separator<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(::)(?s)(.*)$").unwrap();
+    fn parse_comma_list<T, F>(&mut self, close: TokenKind, parse_item: F) -> Result<(Vec<T>, bool), Diagnostic>
+        where F: Fn(&mut Parser<'a>) -> Result<T, Diagnostic>
+    {
+        let mut items = vec![];
+        let mut trailing_comma = false;
+        loop {
+            self.skip_newlines();
+            if self.eat(&close) {
+                return Ok((items, trailing_comma))
+            }
+            items.push(parse_item(self)?);
+            self.skip_newlines();
+            if !self.eat(&TokenKind::Comma) {
+                let closing = format!("`{}`", closing_text(&close));
+                if !self.eat(&close) {
+                    return Err(self.unexpected_here(&["`,`", &closing]))
+                }
+                return Ok((items, false))
+            }
+            trailing_comma = true;
+        }
     }
-    let token_kind = TokenKind::NamespaceSeparator;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_not_operator<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(not\\s)(?s)(.*)$").unwrap();
+    fn parse_token_group(&mut self) -> Result<String, Diagnostic> {
+        let open = match self.peek() {
+            Some(token) if block_keyword::end_token_kind(&token.kind).is_some() => self.bump(),
+            _ => return Err(self.unexpected_here(&["`(`", "`[`", "`{`"])),
+        };
+        let close_kind = block_keyword::end_token_kind(&open.kind).unwrap_or(TokenKind::Unknown);
+        let mut depth = 1;
+        while self.peek().is_some() {
+            let token = self.bump();
+            if token.kind == open.kind {
+                depth += 1;
+            } else if token.kind == close_kind {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(self.source[open.span.end..token.span.start].to_string())
+                }
+            }
+        }
+        let message = format!("unclosed `{}`", self.text(open.span));
+        Err(Diagnostic::error(&message, open.span).with_expected(&format!("`{}`", closing_text(&close_kind))))
     }
-    let token_kind = TokenKind::Not;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_array<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(\\[.*,.*\\]\\n?)(?s)(\\s.*)$").unwrap();
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
     }
-    let token_kind = TokenKind::Collection(Collection::Array);
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_tuple<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(\\(.*,.*\\)\\n?)(?s)(\\s.*)$").unwrap();
+    fn peek_kind(&self) -> Option<&TokenKind> {
+        self.peek().map(|token| &token.kind)
     }
-    let token_kind = TokenKind::Collection(Collection::Tuple);
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_hash_map<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(=\\s*\\{.*,.*\\}\\n?)(?s)(\\s.*)$").unwrap();
+    fn peek_nth_kind(&self, n: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.position + n).map(|token| &token.kind)
     }
-    let token_kind = TokenKind::Collection(Collection::HashMap);
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_derive<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(derive\\(.*\\))(?s)(.*)$").unwrap();
+    fn peek_span(&self) -> Span {
+        self.peek().map_or(self.last_span, |token| token.span)
     }
 
-    if let Some(caps) = RE.captures(syntax) {
-        Some(
-                (
-                    Token::new(TokenKind::Keyword(Keyword::Derive), line_number, false),
-                    (
-                        caps.get(1).map_or("", |m| m.as_str()),
-                        caps.get(2).map_or("", |m| m.as_str()),
-                    )
-                )
-            )
-    } else {
-        None
+    fn check(&self, kind: &TokenKind) -> bool {
+        self.peek_kind() == Some(kind)
     }
-}
 
-fn parse_string<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r#"^(?s)(".*?")(.*)$"#).unwrap();
+    fn check_identifier(&self, name: &str) -> bool {
+        match self.peek() {
+            Some(token) => token.kind == TokenKind::Identifier && self.text(token.span) == name,
+            None => false,
+        }
     }
-    let token_kind = TokenKind::Lexeme(Lexeme::String);
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_borrow<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(borrow\\s)(?s)(.*)$").unwrap();
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        self.position += 1;
+        self.last_span = token.span;
+        token
     }
-    let token_kind = TokenKind::Keyword(Keyword::Borrow);
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_own<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(own\\s)(?s)(.*)$").unwrap();
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.check(kind) {
+            self.bump();
+            true
+        } else {
+            false
+        }
     }
-    let token_kind = TokenKind::Keyword(Keyword::Own);
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_function_call<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^([A-Za-z_0-9]+\\s*\\()(?s)(.*)$").unwrap();
+    fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<Token, Diagnostic> {
+        if self.check(&kind) {
+            Ok(self.bump())
+        } else {
+            Err(self.unexpected_here(&[expected]))
+        }
     }
-    let token_kind = TokenKind::FunctionCall;
-    parse_capture!(syntax, RE, token_kind, line_number, false)
-}
 
-fn parse_operator<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-
-    for parsing in [
-        (
-            "\\+",
-            Token::new(TokenKind::Operator(Operator::Add), line_number, false),
-        ),
-        (
-            "-",
-            Token::new(TokenKind::Operator(Operator::Minus), line_number, false),
-        ),
-        (
-            "\\*",
-            Token::new(TokenKind::Operator(Operator::Multiply), line_number, false),
-        ),
-        (
-            "/",
-            Token::new(TokenKind::Operator(Operator::Divide), line_number, false),
-        ),
-        (
-            "%",
-            Token::new(TokenKind::Operator(Operator::Mod), line_number, false),
-        ),
-    ]
-    .into_iter()
-    {
-        let (pattern, token) = parsing;
-        let full_pattern = format!("^({})(?s)(\\s.*)$", pattern);
+    fn expect_identifier(&mut self, expected: &str) -> Result<(String, Span), Diagnostic> {
+        if self.check(&TokenKind::Identifier) {
+            let token = self.bump();
+            Ok((self.text(token.span).to_string(), token.span))
+        } else {
+            Err(self.unexpected_here(&[expected]))
+        }
+    }
 
-        if let Some(parsed_result) = parse(full_pattern, syntax) {
-            return Some((token.clone(), parsed_result));
+    fn at_continued_line(&self, kind: &TokenKind) -> bool {
+        let mut n = 0;
+        while self.peek_nth_kind(n) == Some(&TokenKind::NewLine) {
+            n += 1;
         }
+        n > 0 && self.peek_nth_kind(n) == Some(kind)
     }
 
-    None
-}
+    fn eat_continued(&mut self, kind: &TokenKind) -> bool {
+        if self.at_continued_line(kind) {
+            self.skip_newlines();
+        }
+        self.eat(kind)
+    }
 
-fn parse_type<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-
-    for parsing in [
-        (
-            "Boolean",
-            Token::new(TokenKind::Literal(LiteralKind::Boolean), line_number, false),
-        ),
-        (
-            "String",
-            Token::new(TokenKind::Literal(LiteralKind::String), line_number, false),
-        ),
-        (
-            "Int32",
-            Token::new(TokenKind::Literal(LiteralKind::Int32), line_number, false),
-        ),
-        (
-            "Int64",
-            Token::new(TokenKind::Literal(LiteralKind::Int64), line_number, false),
-        ),
-        (
-            "UInt32",
-            Token::new(TokenKind::Literal(LiteralKind::UInt32), line_number, false),
-        ),
-        (
-            "UInt64",
-            Token::new(TokenKind::Literal(LiteralKind::UInt64), line_number, false),
-        ),
-        (
-            "Float64",
-            Token::new(TokenKind::Literal(LiteralKind::Float64), line_number, false),
-        ),
-        (
-            "Float32",
-            Token::new(TokenKind::Literal(LiteralKind::Float32), line_number, false),
-        ),
-        (
-            "Usize",
-            Token::new(TokenKind::Literal(LiteralKind::Usize), line_number, false),
-        ),
-        (
-            "Isize",
-            Token::new(TokenKind::Literal(LiteralKind::Isize), line_number, false),
-        ),
-        (
-            "Char",
-            Token::new(TokenKind::Literal(LiteralKind::Char), line_number, false),
-        ),
-        (
-            "Byte",
-            Token::new(TokenKind::Literal(LiteralKind::Byte), line_number, false),
-        ),
-    ]
-    .into_iter()
-    {
-        let (pattern, token) = parsing;
-        let full_pattern = format!("^({}[,|>|)|\\n])(?s)(.*)$", pattern);
+    fn skip_newlines(&mut self) {
+        while self.check(&TokenKind::NewLine) || self.check(&TokenKind::Semi) {
+            self.bump();
+        }
+    }
+
+    fn at_guard(&self) -> bool {
+        matches!(
+            self.peek_kind(),
+            Some(TokenKind::Keyword(Keyword::If)) | Some(TokenKind::Keyword(Keyword::Unless))
+        )
+    }
+
+    fn at_end_of_statement(&self) -> bool {
+        matches!(
+            self.peek_kind(),
+            None |
+            Some(TokenKind::NewLine) |
+            Some(TokenKind::Semi) |
+            Some(TokenKind::Keyword(Keyword::End)) |
+            Some(TokenKind::Keyword(Keyword::Else)) |
+            Some(TokenKind::Keyword(Keyword::Elsif))
+        )
+    }
 
-        if let Some(parsed_result) = parse(full_pattern, syntax) {
-            return Some((token.clone(), parsed_result));
+    fn end_of_statement(&mut self) -> Result<(), Diagnostic> {
+        if !self.at_end_of_statement() {
+            return Err(self.unexpected_here(&["the end of the line"]))
         }
+        if self.check(&TokenKind::NewLine) || self.check(&TokenKind::Semi) {
+            self.bump();
+        }
+        Ok(())
     }
 
-    None
-}
+    fn recover(&mut self, diagnostic: Diagnostic, start: Span) -> Span {
+        self.diagnostics.push(diagnostic);
+        self.synchronize();
+        self.span_from(start)
+    }
 
-fn parse_to_token<'a>(syntax: &'a str, line_number: i32) -> Option<(Token, (&'a str, &'a str))> {
-
-    for parsing in [
-        (
-            "module",
-            Token::new(TokenKind::Keyword(Keyword::Module), line_number, false),
-        ),
-        (
-            "let",
-            Token::new(TokenKind::Keyword(Keyword::Let), line_number, false),
-        ),
-        (
-            "do",
-            Token::new(TokenKind::Keyword(Keyword::Do), line_number, false),
-        ),
-        (
-            "end",
-            Token::new(TokenKind::Keyword(Keyword::End), line_number, false),
-        ),
-        (
-            "trait",
-            Token::new(TokenKind::Keyword(Keyword::Trait), line_number, false),
-        ),
-        (
-            "match",
-            Token::new(TokenKind::Keyword(Keyword::Match), line_number, false),
-        ),
-        (
-            "enum",
-            Token::new(TokenKind::Keyword(Keyword::Enum), line_number, false),
-        ),
-        (
-            "use",
-            Token::new(TokenKind::Keyword(Keyword::Use), line_number, false),
-        ),
-        (
-            "extern crate",
-            Token::new(TokenKind::Keyword(Keyword::ExternCrate), line_number, false),
-        ),
-        (
-            "struct",
-            Token::new(TokenKind::Keyword(Keyword::Struct), line_number, false),
-        ),
-        (
-            "public struct",
-            Token::new(TokenKind::Keyword(Keyword::PublicStruct), line_number, false),
-        ),
-        (
-            "public trait",
-            Token::new(TokenKind::Keyword(Keyword::PublicTrait), line_number, false),
-        ),
-        (
-            "public use",
-            Token::new(TokenKind::Keyword(Keyword::PublicUse), line_number, false),
-        ),
-        (
-            "public module",
-            Token::new(TokenKind::Keyword(Keyword::PublicModule), line_number, false),
-        ),
-        (
-            "function",
-            Token::new(TokenKind::Keyword(Keyword::Function), line_number, false),
-        ),
-        (
-            "public function",
-            Token::new(TokenKind::Keyword(Keyword::PublicFunction), line_number, false),
-        ),
-        (
-            "public enum",
-            Token::new(TokenKind::Keyword(Keyword::PublicEnum), line_number, false),
-        ),
-        (
-            "public",
-            Token::new(TokenKind::Keyword(Keyword::Public), line_number, false),
-        ),
-        (
-            "implements",
-            Token::new(TokenKind::Keyword(Keyword::Implements), line_number, false),
-        ),
-        (
-            "inherits",
-            Token::new(TokenKind::Keyword(Keyword::Inherits), line_number, false),
-        ),
-        (
-            "if",
-            Token::new(TokenKind::Keyword(Keyword::If), line_number, false),
-        ),
-        (
-            "then",
-            Token::new(TokenKind::Keyword(Keyword::Then), line_number, false),
-        ),
-        (
-            "else",
-            Token::new(TokenKind::Keyword(Keyword::Else), line_number, false),
-        ),
-        (
-            "for",
-            Token::new(TokenKind::Keyword(Keyword::For), line_number, false),
-        ),
-        (
-            "in",
-            Token::new(TokenKind::Keyword(Keyword::In), line_number, false),
-        ),
-        (
-            "let",
-            Token::new(TokenKind::Keyword(Keyword::Let), line_number, false),
-        ),
-        (
-            "equal",
-            Token::new(TokenKind::Equal, line_number, false),
-        ),
-        (
-            "not equal",
-            Token::new(TokenKind::NotEqual, line_number, false),
-        ),
-        (
-            "mutable",
-            Token::new(TokenKind::Keyword(Keyword::Mutable), line_number, false),
-        ),
-        (
-            "own",
-            Token::new(TokenKind::Keyword(Keyword::Own), line_number, false),
-        ),
-        (
-            "return",
-            Token::new(TokenKind::Keyword(Keyword::Return), line_number, false),
-        ),
-        (
-            "=",
-            Token::new(TokenKind::Assign, line_number, false),
-        ),
-        (
-            ":",
-            Token::new(TokenKind::DoubleDot, line_number, false),
-        ),
-        (
-            "=>",
-            Token::new(TokenKind::FatArrow, line_number, false),
-        ),
-        (
-            "\\|>",
-            Token::new(TokenKind::PipeLine, line_number, false),
-        ),
-    ]
-    .into_iter()
-    {
-        let (pattern, token) = parsing;
-        let full_pattern = format!("^({})(?s)(\\s.*)$", pattern);
+    fn synchronize(&mut self) {
+        let start = self.position;
+        let mut depth = 0;
+        let mut in_elsif = false;
+        while let Some(kind) = self.peek_kind() {
+            match kind {
+                TokenKind::NewLine if depth == 0 => {
+                    self.bump();
+                    return
+                },
+                TokenKind::Keyword(Keyword::End) if depth == 0 => return,
+                TokenKind::Keyword(Keyword::Else) |
+                TokenKind::Keyword(Keyword::Elsif) if depth == 0 && self.position > start => return,
+                TokenKind::Keyword(Keyword::Elsif) => in_elsif = true,
+                TokenKind::Keyword(Keyword::End) => depth -= 1,
+                TokenKind::Keyword(Keyword::Then) | TokenKind::Keyword(Keyword::Do) if in_elsif => in_elsif = false,
+                TokenKind::Keyword(Keyword::Then) => depth += 1,
+                TokenKind::Keyword(Keyword::Do) if self.previous_kind() != Some(&TokenKind::Keyword(Keyword::Else)) => {
+                    depth += 1
+                },
+                _ => {},
+            }
+            self.bump();
+        }
+    }
+
+    fn previous_kind(&self) -> Option<&TokenKind> {
+        self.position.checked_sub(1).map(|index| &self.tokens[index].kind)
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        start.merge(self.last_span)
+    }
 
-        if let Some(parsed_result) = parse(full_pattern, syntax) {
-            return Some((token.clone(), parsed_result));
+    fn text(&self, span: Span) -> &'a str {
+        span.text(self.source)
+    }
+
+    fn unexpected_here(&self, expected: &[&str]) -> Diagnostic {
+        match self.peek() {
+            Some(token) => self.unexpected(token, expected),
+            None => {
+                let end = self.source.len();
+                let last_line_start = self.source.rfind('\n').map_or(0, |index| index + 1);
+                let line = self.source.matches('\n').count() + 1;
+                let column = self.source[last_line_start..].chars().count() + 1;
+                let span = Span::new(self.file, end, end, line, column);
+                with_expected(Diagnostic::error("unexpected end of file", span), expected)
+            },
         }
     }
 
-    None
+    fn unexpected(&self, token: &Token, expected: &[&str]) -> Diagnostic {
+        let message = match token.kind {
+            TokenKind::NewLine => "unexpected end of line".to_string(),
+            _ => format!("unexpected `{}`", self.text(token.span)),
+        };
+        with_expected(Diagnostic::error(&message, token.span), expected)
+    }
+}
+
+fn negate(condition: Expr) -> Expr {
+    let span = condition.span;
+    let operand = parenthesize(condition, false);
+    Expr::new(ExprKind::Unary { op: UnaryOp::Not, operand: Box::new(operand) }, span)
 }
 
-fn parse<'a>(pattern: String, syntax: &'a str) -> Option<(&'a str, &'a str)> {
-    let re = Regex::new(&pattern).unwrap();
-    if let Some(caps) = re.captures(syntax) {
-        Some((
-            caps.get(1).map_or("", |m| m.as_str()),
-            caps.get(2).map_or("", |m| m.as_str()),
-        ))
+fn parenthesize(expr: Expr, receiver: bool) -> Expr {
+    let needs_parens = match expr.kind {
+        ExprKind::Binary { .. } | ExprKind::Range { .. } => true,
+        ExprKind::Unary { .. } => receiver,
+        _ => false,
+    };
+    if needs_parens {
+        let span = expr.span;
+        Expr::new(ExprKind::Paren(Box::new(expr)), span)
     } else {
-        None
+        expr
     }
 }
 
-fn parse_macro_body<'a>(syntax: &'a str, tree: &mut Node, line_number: i32) -> Result<&'a str, &'a str> {
-    let mut full_code = syntax;
-    lazy_static! {
-        static ref RE: Regex = Regex::new("^(\\s*)(?s)(.*)$").unwrap();
+fn with_expected(diagnostic: Diagnostic, expected: &[&str]) -> Diagnostic {
+    expected.iter().fold(diagnostic, |diagnostic, expected| diagnostic.with_expected(expected))
+}
+
+fn pass_block(expr: Expr, block: Expr) -> Result<ExprKind, Diagnostic> {
+    let span = block.span;
+    let arg = Arg { name: None, value: block, span };
+    Ok(match expr.kind {
+        ExprKind::Call { callee, mut args } => {
+            args.push(arg);
+            ExprKind::Call { callee, args }
+        },
+        ExprKind::MethodCall { receiver, method, generics, mut args } => {
+            args.push(arg);
+            ExprKind::MethodCall { receiver, method, generics, args }
+        },
+        ExprKind::Field { receiver, name } => {
+            ExprKind::MethodCall { receiver, method: name, generics: vec![], args: vec![arg] }
+        },
+        ExprKind::Path(path) => {
+            let callee = Expr::new(ExprKind::Path(path), expr.span);
+            ExprKind::Call { callee: Box::new(callee), args: vec![arg] }
+        },
+        _ => return Err(Diagnostic::error("a block can only be passed to a function or method call", span)),
+    })
+}
+
+fn closing_text(kind: &TokenKind) -> &'static str {
+    match kind {
+        TokenKind::CloseParen => ")",
+        TokenKind::CloseBracket => "]",
+        TokenKind::CloseBrace => "}",
+        TokenKind::Or => "|",
+        _ => "",
     }
-    let token_kind = TokenKind::Whitespace;
-    let result = parse_capture!(full_code, RE, token_kind, line_number, false);
+}
 
-    if let Some(result_parsed) = result {
-        full_code = (result_parsed.1).1;
-    } else {
-        return Err("parsing error");
-    }
-    let mut open_character = &full_code[..1];
-
-    let mut new_line_number = line_number;
-    let mut begin_macro = 0;
-    let mut end_macro = 0;
-    let mut code = full_code;
-    let mut macro_body = vec![];
-    let mut chari = "";
-    loop {
-        chari = &code[..1];
-        if chari == open_character {
-            begin_macro += 1;
-        }
-        if chari == block_keyword::end_character(open_character) {
-            end_macro += 1;
-        }
-        if chari == "\n" {
-            new_line_number += 1;
-        }
-        code = &code[1..];
-        macro_body.push(chari);
-
-        if begin_macro == end_macro && begin_macro > 0 && end_macro > 0 {
-            full_code = code;
-            if &full_code[..1] == "\n" {
-                macro_body.push(";");
+#[test]
+fn test_parse() {
+    let source = "struct Cat inherits Animal do\n  playful as Boolean\nend\n\nlet cat = Cat::new(name: \"Tom\")\n";
+    let module = parse(FileId(0), source).unwrap();
+
+    assert_eq!(module.items.len(), 2);
+    match &module.items[0] {
+        Item::Struct(item) => {
+            assert_eq!(item.name, "Cat");
+            assert_eq!(item.parent.as_ref().map(|parent| parent.name()), Some("Animal"));
+            assert_eq!(item.fields[0].name, "playful");
+            assert_eq!(item.fields[0].ty.name, "Boolean");
+        },
+        item => panic!("expected a struct, found {:?}", item),
+    }
+    match &module.items[1] {
+        Item::Stmt(Stmt::Let(binding)) => {
+            assert_eq!(binding.name, "cat");
+            match &binding.value.kind {
+                ExprKind::Call { args, .. } => assert_eq!(args[0].name.as_deref(), Some("name")),
+                kind => panic!("expected a call, found {:?}", kind),
             }
-            tree.children.push(
-                Node {
-                    token: Token::new(TokenKind::MacroBody, new_line_number, false),
-                    children: vec![],
-                    data: Some(macro_body.join(""))
-                }
-            );
-            break;
-        }
+            assert_eq!(binding.span.text(source), "let cat = Cat::new(name: \"Tom\")");
+        },
+        item => panic!("expected a let statement, found {:?}", item),
     }
 
-    Ok(full_code)
-}
\ No newline at end of file
+    let module = parse(FileId(0), "let total = xs |> .iter().sum::<Int32>()\nlet names = xs.collect::<Vec<Vec<_>>>()\n").unwrap();
+    let turbofish = module.items
+        .iter()
+        .map(|item| match item {
+            Item::Stmt(Stmt::Let(binding)) => match &binding.value.kind {
+                ExprKind::MethodCall { method, generics, .. } => {
+                    let generics = generics.iter().map(|ty| ty.name.as_str()).collect::<Vec<_>>();
+                    format!("{}::<{}>", method, generics.join(", "))
+                },
+                kind => panic!("expected a method call, found {:?}", kind),
+            },
+            item => panic!("expected a let statement, found {:?}", item),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(turbofish, ["sum::<Int32>", "collect::<Vec>"]);
+}
+
+#[test]
+fn test_parse_errors() {
+    let diagnostics = parse(FileId(0), "struct Cat do\n  playful Boolean\nend").unwrap_err();
+    let error = diagnostics.iter().next().unwrap();
+    assert_eq!(error.message, "unexpected `Boolean`");
+    assert_eq!(error.expected, vec!["`as`".to_string()]);
+    assert_eq!((error.span.line, error.span.column), (2, 11));
+
+    let diagnostics = parse(FileId(0), "if ready then\n  go()\n").unwrap_err();
+    assert_eq!(diagnostics.to_string(), "error: unexpected end of file at 3:1, expected `end`");
+}
+
+#[test]
+fn test_parse_recovery() {
+    let source = "\
+struct Cat do
+  playful Boolean
+  age as Int32
+end
+
+function meow(times as Int32) do
+  let = 3
+  if times > 1 then
+    println!(\"meow\") )
+  end
+  return times
+end
+
+let price = 5€
+";
+    let (module, diagnostics) = parse_partial(FileId(0), source);
+    let messages = diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect::<Vec<_>>();
+    assert_eq!(messages, vec![
+        "error: unexpected `Boolean` at 2:11, expected `as`",
+        "error: unexpected `=` at 7:7, expected a variable name",
+        "error: unexpected `)` at 9:22, expected the end of the line",
+        "error: unexpected character `€` at 14:14",
+    ]);
+
+    assert_eq!(module.items.len(), 3);
+    match &module.items[0] {
+        Item::Struct(item) => assert_eq!(item.fields.iter().map(|field| &field.name[..]).collect::<Vec<_>>(), ["age"]),
+        item => panic!("expected a struct, found {:?}", item),
+    }
+    match &module.items[1] {
+        Item::Function(function) => {
+            let stmts = &function.body.as_ref().unwrap().stmts;
+            assert_eq!(stmts.len(), 3);
+            assert!(matches!(stmts[0], Stmt::Error(span) if span.text(source) == "let = 3\n"));
+            assert!(matches!(stmts[2], Stmt::Expr(Expr { kind: ExprKind::Return(_), .. })));
+        },
+        item => panic!("expected a function, found {:?}", item),
+    }
+    assert!(matches!(module.items[2], Item::Stmt(Stmt::Let(_))));
+}
+
+#[test]
+fn test_parse_tuples() {
+    let module = parse(FileId(0), "let one = (1,\n)\nlet paren = (1\n)\nlet pair = (1, 2,)\n").unwrap();
+    let kinds = module.items
+        .iter()
+        .map(|item| match item {
+            Item::Stmt(Stmt::Let(binding)) => match &binding.value.kind {
+                ExprKind::Tuple(items) => format!("tuple of {}", items.len()),
+                ExprKind::Paren(_) => "paren".to_string(),
+                kind => panic!("expected a tuple or parentheses, found {:?}", kind),
+            },
+            item => panic!("expected a let statement, found {:?}", item),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, ["tuple of 1", "paren", "tuple of 2"]);
+}
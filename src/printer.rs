@@ -0,0 +1,144 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrintOptions {
+    pub indent_width: usize,
+    pub blank_line_between_items: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Printer {
+    pub options: PrintOptions,
+    output: String,
+    depth: usize,
+    pending_blank_line: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> PrintOptions {
+        PrintOptions { indent_width: 4, blank_line_between_items: true }
+    }
+}
+
+impl Printer {
+    pub fn new(options: PrintOptions) -> Printer {
+        Printer { options, output: String::new(), depth: 0, pending_blank_line: false }
+    }
+
+    pub fn line(&mut self, text: &str) {
+        let mut in_literal = false;
+        for line in text.split('\n') {
+            let starts_in_literal = in_literal;
+            in_literal = ends_in_literal(line, in_literal);
+            if starts_in_literal {
+                self.output.push_str(if in_literal { line } else { line.trim_end() });
+            } else {
+                self.flush_blank_line(line);
+                let line = if in_literal { line } else { line.trim_end() };
+                if !line.is_empty() {
+                    self.output.push_str(&" ".repeat(self.depth * self.options.indent_width));
+                    self.output.push_str(line);
+                }
+            }
+            self.output.push('\n');
+        }
+    }
+
+    pub fn blank_line(&mut self) {
+        self.pending_blank_line = true;
+    }
+
+    pub fn indent(&mut self) {
+        self.depth += 1;
+    }
+
+    pub fn dedent(&mut self) {
+        self.depth -= 1;
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    fn flush_blank_line(&mut self, next_line: &str) {
+        if !self.pending_blank_line {
+            return
+        }
+        self.pending_blank_line = false;
+        let after_open = self.output.trim_end_matches('\n').ends_with('{');
+        let before_close = next_line.trim_start().starts_with('}');
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") && !after_open && !before_close {
+            self.output.push('\n');
+        }
+    }
+}
+
+fn ends_in_literal(line: &str, mut in_literal: bool) -> bool {
+    let mut chars = line.chars();
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' if in_literal => {
+                chars.next();
+            },
+            '"' => in_literal = !in_literal,
+            '\'' if !in_literal => {
+                let rest = chars.as_str();
+                let length = if rest.starts_with('\\') { 3 } else { 2 };
+                if rest.chars().nth(length - 1) == Some('\'') {
+                    chars.nth(length - 1);
+                }
+            },
+            _ => {},
+        }
+    }
+    in_literal
+}
+
+pub fn dedent_lines(text: &str) -> Vec<&str> {
+    let lines = text
+        .trim_matches('\n')
+        .trim_end()
+        .lines()
+        .collect::<Vec<_>>();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .into_iter()
+        .map(|line| if line.trim().is_empty() { "" } else { &line[indent..] })
+        .collect()
+}
+
+#[test]
+fn test_printer() {
+    let mut printer = Printer::new(PrintOptions { indent_width: 2, blank_line_between_items: true });
+    printer.blank_line();
+    printer.line("mod shapes {");
+    printer.indent();
+    printer.blank_line();
+    printer.line("struct Square;");
+    printer.blank_line();
+    printer.blank_line();
+    printer.line("fn area() {\n    4\n}");
+    printer.blank_line();
+    printer.dedent();
+    printer.line("}");
+
+    assert_eq!(printer.finish(), "mod shapes {\n  struct Square;\n\n  fn area() {\n      4\n  }\n}\n");
+    assert_eq!(dedent_lines("\n    (a) => {\n      a\n    };\n  "), vec!["(a) => {", "  a", "};"]);
+}
+
+#[test]
+fn test_printer_string_literals() {
+    use crate::parser;
+    use crate::source::FileId;
+    use crate::transpiler::transpile;
+
+    let source = "function banner() do\n  if ready then\n    let text = \"a\n  b  \n'c'\"\n  end\nend\n";
+    let module = parser::parse(FileId(0), source).unwrap();
+    assert_eq!(
+        transpile(&module),
+        "fn banner() {\n    if ready {\n        let text = \"a\n  b  \n'c'\";\n    }\n}\n"
+    );
+}
@@ -0,0 +1,36 @@
+pub fn candidates(scope: &[String], segments: &[String]) -> Vec<Vec<String>> {
+    match segments.first().map(|segment| segment.as_str()) {
+        None => vec![],
+        Some("crate") => vec![segments[1..].to_vec()],
+        Some("self") | Some("super") => {
+            let mut base = scope.to_vec();
+            let mut rest = segments;
+            while let Some((segment, remaining)) = rest.split_first() {
+                match segment.as_str() {
+                    "self" => {},
+                    "super" => {
+                        if base.pop().is_none() {
+                            return vec![]
+                        }
+                    },
+                    _ => break,
+                }
+                rest = remaining;
+            }
+            base.extend(rest.iter().cloned());
+            vec![base]
+        },
+        Some(_) => vec![scope.iter().chain(segments).cloned().collect()],
+    }
+}
+
+#[test]
+fn test_candidates() {
+    let path = |text: &str| text.split("::").map(|segment| segment.to_string()).collect::<Vec<_>>();
+    let scope = path("zoo::cats");
+
+    assert_eq!(candidates(&scope, &path("Animal")), [path("zoo::cats::Animal")]);
+    assert_eq!(candidates(&scope, &path("super::Animal")), [path("zoo::Animal")]);
+    assert_eq!(candidates(&scope, &path("crate::Animal")), [path("Animal")]);
+    assert!(candidates(&[], &path("super::Animal")).is_empty());
+}
@@ -0,0 +1,160 @@
+use crate::arguments;
+use crate::ast::Module;
+use crate::constructors;
+use crate::diagnostic::Diagnostics;
+use crate::imports;
+use crate::inheritance;
+use crate::parser;
+use crate::printer::PrintOptions;
+use crate::source::{ FileId, SourceMap };
+use crate::traits;
+use crate::transpiler;
+use crate::variants;
+
+pub const STDIN_FILE_NAME: &str = "<stdin>";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub file_name: String,
+    pub print_options: PrintOptions,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    options: Options,
+    source_map: SourceMap,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { file_name: STDIN_FILE_NAME.to_string(), print_options: PrintOptions::default() }
+    }
+}
+
+impl Options {
+    pub fn with_file_name(file_name: &str) -> Options {
+        Options { file_name: file_name.to_string(), ..Options::default() }
+    }
+
+    pub fn indent_width(mut self, indent_width: usize) -> Options {
+        self.print_options.indent_width = indent_width;
+        self
+    }
+
+    pub fn blank_line_between_items(mut self, blank_line_between_items: bool) -> Options {
+        self.print_options.blank_line_between_items = blank_line_between_items;
+        self
+    }
+}
+
+impl Session {
+    pub fn new(options: Options) -> Session {
+        Session { options, source_map: SourceMap::new() }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn add_file(&mut self, name: &str, source: &str) -> FileId {
+        self.source_map.add_file(name, source)
+    }
+
+    pub fn parse(&mut self, source: &str) -> Result<Module, Diagnostics> {
+        let file = self.source_map.add_file(&self.options.file_name, source);
+        self.parse_file(file)
+    }
+
+    pub fn parse_file(&self, file: FileId) -> Result<Module, Diagnostics> {
+        parser::parse(file, &self.source_map.file(file).text)
+    }
+
+    pub fn parse_file_partial(&self, file: FileId) -> (Module, Diagnostics) {
+        parser::parse_partial(file, &self.source_map.file(file).text)
+    }
+
+    pub fn analyze(&self, module: &mut Module) -> Result<(), Diagnostics> {
+        let passes: [fn(&mut Module) -> Diagnostics; 6] = [
+            inheritance::flatten,
+            constructors::generate,
+            traits::implement_fields,
+            arguments::resolve_calls,
+            variants::qualify,
+            imports::insert_collections,
+        ];
+        for pass in &passes {
+            let diagnostics = pass(module);
+            if diagnostics.has_errors() {
+                return Err(diagnostics)
+            }
+        }
+        Ok(())
+    }
+
+    pub fn transpile(&self, module: &Module) -> String {
+        transpiler::transpile_with_options(module, self.options.print_options)
+    }
+
+    pub fn compile(&mut self, source: &str) -> Result<String, Diagnostics> {
+        let mut module = self.parse(source)?;
+        self.analyze(&mut module)?;
+        Ok(self.transpile(&module))
+    }
+}
+
+#[test]
+fn test_compile() {
+    let mut session = Session::default();
+    let rust = session.compile("module shapes do\n  let side = 4\nend\n");
+    assert_eq!(rust, Ok("mod shapes {\n    let side = 4;\n}\n".to_string()));
+
+    let mut session = Session::new(Options::with_file_name("broken.am"));
+    let diagnostics = session.compile("let side = \"four").unwrap_err();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics.render(session.source_map()),
+        "error: unterminated string\n --> broken.am:1:12\n  |\n1 | let side = \"four\n  |            ^^^^^ expected `\"`\n"
+    );
+}
+
+#[test]
+fn test_print_options() {
+    let source = "module shapes do\n  struct Square do\n    side as Int32\n  end\n  let side = 4\nend\n";
+
+    let mut session = Session::new(Options::default().indent_width(2));
+    assert_eq!(
+        session.compile(source).unwrap(),
+        "mod shapes {\n  #[derive(Debug, Clone, Default, PartialEq)]\n  struct Square {\n    side: i32,\n  }\n\n  \
+         impl Square {\n    pub fn new(side: i32) -> Self {\n      Self { side }\n    }\n  }\n\n  let side = 4;\n}\n"
+    );
+
+    let mut session = Session::new(Options::default().blank_line_between_items(false));
+    assert!(session.compile(source).unwrap().contains("    }\n    let side = 4;\n"));
+}
+
+#[test]
+fn test_parse_spans() {
+    use crate::ast::{ Item, Stmt };
+
+    let mut session = Session::default();
+    let first = session.add_file("first.am", "let side = 4\n");
+    let second = session.add_file("second.am", "module shapes do\n  println!(\"{}\", side)\nend\n");
+
+    assert!(session.parse_file(first).is_ok());
+    let module = session.parse_file(second).unwrap();
+    let span = match &module.items[0] {
+        Item::Module(shapes) => match &shapes.items[0] {
+            Item::Stmt(Stmt::Expr(expr)) => expr.span,
+            item => panic!("expected a macro call, found {:?}", item),
+        },
+        item => panic!("expected a module, found {:?}", item),
+    };
+
+    assert_eq!(span.file, second);
+    assert_eq!((span.line, span.column), (2, 3));
+    assert_eq!(session.source_map().file(second).span_text(span), "println!(\"{}\", side)");
+}
@@ -0,0 +1,88 @@
+use crate::token::Span;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(pub usize);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceFile {
+    pub id: FileId,
+    pub name: String,
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceFile {
+    pub fn new(id: FileId, name: &str, text: &str) -> SourceFile {
+        let line_starts =
+            std::iter::once(0)
+                .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+                .collect();
+        SourceFile { id, name: name.to_string(), text: text.to_string(), line_starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    pub fn line_index(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        }
+    }
+
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line_index = self.line_index(offset);
+        let line_start = self.line_starts[line_index];
+        (line_index + 1, self.text[line_start..offset].chars().count() + 1)
+    }
+
+    pub fn line_text(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line).map_or(self.text.len(), |next| next - 1);
+        self.text[start..end].trim_end_matches('\r')
+    }
+
+    pub fn span_text(&self, span: Span) -> &str {
+        &self.text[span.start..span.end]
+    }
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    pub fn add_file(&mut self, name: &str, text: &str) -> FileId {
+        let id = FileId(self.files.len());
+        self.files.push(SourceFile::new(id, name, text));
+        id
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    pub fn files(&self) -> std::slice::Iter<'_, SourceFile> {
+        self.files.iter()
+    }
+}
+
+#[test]
+fn test_line_column() {
+    let mut source_map = SourceMap::new();
+    let id = source_map.add_file("cat.am", "struct Cat do\n  playful as Boolean\nend");
+    let file = source_map.file(id);
+
+    assert_eq!(file.line_count(), 3);
+    assert_eq!(file.line_column(0), (1, 1));
+    assert_eq!(file.line_column(16), (2, 3));
+    assert_eq!(file.line_column(14), (2, 1));
+    assert_eq!(file.line_text(2), "  playful as Boolean");
+    assert_eq!(file.line_text(3), "end");
+}
@@ -1,27 +1,28 @@
 use crate::keyword;
 use crate::lexeme;
+use crate::source::FileId;
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Span {
-    line: i32
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
-    pub eos: bool
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum TokenKind {
     Assign,
-    Root,
     LineComment,
     BlockComment,
-    Whitespace,
     Identifier,
-    IdentifierEnd,
     NewLine,
     Literal(LiteralKind),
     Lexeme(lexeme::Lexeme),
@@ -85,10 +86,8 @@ pub enum TokenKind {
     PercentEq,
     Percent,
     Keyword(keyword::Keyword),
-    TypeWithGeneric,
     Macro,
     MacroRules,
-    MacroBody,
     FunctionCall,
     PipeLine,
     Unknown,
@@ -127,8 +126,31 @@ pub enum Collection {
     Vector
 }
 
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span { file, start, end, line, column }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+
+    pub fn merge(&self, other: Span) -> Span {
+        let (first, last) = if self.start <= other.start { (*self, other) } else { (other, *self) };
+        Span { end: first.end.max(last.end), ..first }
+    }
+}
+
 impl Token {
-    pub fn new(kind: TokenKind, line: i32, eos: bool) -> Token {
-        Token { kind, span: Span { line }, eos }
+    pub fn new(kind: TokenKind, span: Span) -> Token {
+        Token { kind, span }
     }
 }
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use crate::ast::{ Module, Item, Struct, Field, Trait, Impl, Type, Expr, ExprKind, UnaryOp };
+use crate::diagnostic::{ Diagnostic, Diagnostics };
+use crate::resolve;
+
+type ItemKey = Vec<String>;
+
+struct Items {
+    traits: HashMap<ItemKey, Trait>,
+    structs: HashMap<ItemKey, Struct>,
+}
+
+pub fn implement_fields(module: &mut Module) -> Diagnostics {
+    let mut items = Items { traits: HashMap::new(), structs: HashMap::new() };
+    collect_items(&module.items, &mut vec![], &mut items);
+
+    let mut diagnostics = Diagnostics::new();
+    implement_items(&mut module.items, &mut vec![], &items, &mut diagnostics);
+    diagnostics
+}
+
+fn collect_items(items: &[Item], scope: &mut Vec<String>, collected: &mut Items) {
+    for item in items {
+        let key = |name: &str| scope.iter().cloned().chain(Some(name.to_string())).collect::<ItemKey>();
+        match item {
+            Item::Trait(item) => {
+                collected.traits.entry(key(&item.name)).or_insert_with(|| item.clone());
+            },
+            Item::Struct(item) => {
+                collected.structs.entry(key(&item.name)).or_insert_with(|| item.clone());
+            },
+            Item::Module(module) => {
+                scope.push(module.name.clone().unwrap_or_default());
+                collect_items(&module.items, scope, collected);
+                scope.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+fn implement_items(items: &mut [Item], scope: &mut Vec<String>, collected: &Items, diagnostics: &mut Diagnostics) {
+    for item in items {
+        match item {
+            Item::Impl(item) => implement(item, scope, collected, diagnostics),
+            Item::Trait(item) => {
+                let fields = item.fields.clone();
+                for body in item.functions.iter_mut().filter_map(|function| function.body.as_mut()) {
+                    body.walk_mut(&mut |expr| use_accessors(expr, &fields, diagnostics));
+                }
+            },
+            Item::Module(module) => {
+                scope.push(module.name.clone().unwrap_or_default());
+                implement_items(&mut module.items, scope, collected, diagnostics);
+                scope.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+fn implement(item: &mut Impl, scope: &[String], collected: &Items, diagnostics: &mut Diagnostics) {
+    item.accessors.clear();
+    let trait_item = match &item.trait_name {
+        Some(trait_name) => match lookup(&collected.traits, scope, &trait_name.segments) {
+            Some(trait_item) => trait_item,
+            None => return,
+        },
+        None => return,
+    };
+
+    let required = trait_item.fields
+        .iter()
+        .filter(|field| !item.functions.iter().any(|function| function.name == field.name))
+        .collect::<Vec<_>>();
+    if required.is_empty() {
+        return
+    }
+
+    let segments = item.self_ty.name.split("::").map(|segment| segment.to_string()).collect::<Vec<_>>();
+    let struct_item = match lookup(&collected.structs, scope, &segments) {
+        Some(struct_item) => struct_item,
+        None => {
+            let message = format!(
+                "cannot find struct `{}` to implement the fields of trait `{}`", item.self_ty.name, trait_item.name
+            );
+            diagnostics.push(Diagnostic::error(&message, item.self_ty.span));
+            return
+        },
+    };
+
+    for field in required {
+        match struct_item.fields.iter().find(|candidate| candidate.name == field.name) {
+            Some(candidate) if same_type(&candidate.ty, &field.ty) => item.accessors.push(field.clone()),
+            Some(candidate) => {
+                let message = format!(
+                    "field `{}` of `{}` has type `{}`, but trait `{}` expects `{}`",
+                    field.name, struct_item.name, type_name(&candidate.ty), trait_item.name, type_name(&field.ty)
+                );
+                diagnostics.push(Diagnostic::error(&message, item.self_ty.span));
+            },
+            None => {
+                let message = format!(
+                    "struct `{}` is missing field `{}` required by trait `{}`",
+                    struct_item.name, field.name, trait_item.name
+                );
+                let note = format!(
+                    "add `{} as {}` to `{}` or define `{}` in this block",
+                    field.name, type_name(&field.ty), struct_item.name, field.name
+                );
+                diagnostics.push(Diagnostic::error(&message, item.self_ty.span).with_note(&note));
+            },
+        }
+    }
+}
+
+fn use_accessors(expr: &mut Expr, fields: &[Field], diagnostics: &mut Diagnostics) {
+    let span = expr.span;
+    match &mut expr.kind {
+        ExprKind::Field { receiver, name } if is_self(receiver) && fields.iter().any(|field| &field.name == name) => {
+            let receiver = receiver.clone();
+            expr.kind = ExprKind::MethodCall { receiver, method: name.clone(), generics: vec![], args: vec![] };
+        },
+        ExprKind::Assign { target, .. } => {
+            if let Some(accessor) = mutable_accessor(target, fields, diagnostics) {
+                let operand = Box::new(accessor);
+                **target = Expr::new(ExprKind::Unary { op: UnaryOp::Own, operand }, span);
+            }
+        },
+        ExprKind::Unary { op: UnaryOp::BorrowMut, operand } => {
+            if let Some(accessor) = mutable_accessor(operand, fields, diagnostics) {
+                *expr = accessor;
+            }
+        },
+        _ => {},
+    }
+}
+
+fn mutable_accessor(expr: &Expr, fields: &[Field], diagnostics: &mut Diagnostics) -> Option<Expr> {
+    let (receiver, method) = match &expr.kind {
+        ExprKind::MethodCall { receiver, method, args, .. } if args.is_empty() && is_self(receiver) => (receiver, method),
+        _ => return None,
+    };
+    let field = fields.iter().find(|field| &field.name == method)?;
+    if !field.mutable {
+        let message = format!("trait field `{}` is not `mutable`", field.name);
+        diagnostics.push(Diagnostic::error(&message, expr.span));
+        return None
+    }
+    let method = format!("{}_mut", method);
+    let kind = ExprKind::MethodCall { receiver: receiver.clone(), method, generics: vec![], args: vec![] };
+    Some(Expr::new(kind, expr.span))
+}
+
+fn is_self(expr: &Expr) -> bool {
+    matches!(&expr.kind, ExprKind::Path(path) if path.segments == ["self"])
+}
+
+fn lookup<'a, T>(items: &'a HashMap<ItemKey, T>, scope: &[String], segments: &[String]) -> Option<&'a T> {
+    resolve::candidates(scope, segments)
+        .iter()
+        .find_map(|candidate| items.get(candidate))
+}
+
+fn same_type(left: &Type, right: &Type) -> bool {
+    left.name == right.name &&
+        left.generics.len() == right.generics.len() &&
+        left.generics.iter().zip(&right.generics).all(|(left, right)| same_type(left, right))
+}
+
+fn type_name(ty: &Type) -> String {
+    if ty.generics.is_empty() {
+        ty.name.clone()
+    } else {
+        let generics = ty.generics.iter().map(type_name).collect::<Vec<_>>();
+        format!("{}<{}>", ty.name, generics.join(", "))
+    }
+}
+
+#[test]
+fn test_implement_fields() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+trait Naming do
+  public name as String
+  public nickname as Option<String>
+end
+
+module zoo do
+  struct Cat do
+    name as String
+    age as Int32
+  end
+
+  implements super::Naming for Cat do
+    function nickname(borrow self) as Option<String> do
+      None
+    end
+  end
+
+  struct Dog do
+    age as Int32
+  end
+
+  implements crate::Naming for Dog do
+  end
+end
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    let diagnostics = implement_fields(&mut module);
+    let messages = diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>();
+    assert_eq!(messages, [
+        "struct `Dog` is missing field `name` required by trait `Naming`",
+        "struct `Dog` is missing field `nickname` required by trait `Naming`",
+    ]);
+
+    let accessors = match &module.items[1] {
+        Item::Module(zoo) => match &zoo.items[1] {
+            Item::Impl(item) => item.accessors.iter().map(|field| field.name.clone()).collect::<Vec<_>>(),
+            item => panic!("expected an implementation, found {:?}", item),
+        },
+        item => panic!("expected a module, found {:?}", item),
+    };
+    assert_eq!(accessors, ["name"]);
+}
+
+#[test]
+fn test_trait_field_accessors() {
+    use crate::parser;
+    use crate::source::FileId;
+    use crate::transpiler::transpile;
+
+    let source = "\
+trait Naming do
+  name as String
+  mutable visits as Int32
+  label as String
+
+  function show(borrow self) as String do
+    self.name.clone()
+  end
+
+  function visit(mutable borrow self) do
+    self.visits += 1
+    let visits = mutable borrow self.visits
+    self.label = self.name.clone()
+  end
+end
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    let diagnostics = implement_fields(&mut module);
+    let messages = diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>();
+    assert_eq!(messages, ["trait field `label` is not `mutable`"]);
+
+    let rust = transpile(&module);
+    assert!(rust.contains("    fn show(&self) -> String {\n        self.name().clone()\n    }"));
+    assert!(rust.contains("        *self.visits_mut() += 1;\n        let visits = self.visits_mut();\n"));
+}
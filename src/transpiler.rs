@@ -1,146 +1,1393 @@
-use std::str;
-
-use crate::ast::Node;
-use crate::token::{ TokenKind, LiteralKind, Operator, Collection };
-use crate::keyword::Keyword;
-use crate::lexeme::Lexeme;
-
-pub fn transpile(ast: Node) -> String {
-    let mut syntax = vec![];
-    for node in ast.children {
-        if let Some(node_data) = &node.data {
-            syntax.push(
-                match node.token.kind {
-                    TokenKind::Keyword(Keyword::Module) => "mod".to_string(),
-                    TokenKind::Keyword(Keyword::Public) => "pub".to_string(),
-                    TokenKind::Keyword(Keyword::Trait) => "trait".to_string(),
-                    TokenKind::Keyword(Keyword::PublicTrait) => "pub trait".to_string(),
-                    TokenKind::Keyword(Keyword::Struct) => {
-                        "#[derive(Debug, Clone, Default, PartialEq)]\nstruct".to_string()
-                    },
-                    TokenKind::Keyword(Keyword::PublicStruct) => {
-                        "#[derive(Debug, Clone, Default, PartialEq)]\npub struct".to_string()
-                    },
-                    TokenKind::Keyword(Keyword::Enum) => {
-                        "#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]\nenum".to_string()
-                    },
-                    TokenKind::Keyword(Keyword::PublicEnum) => {
-                        "#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]\npub enum".to_string()
-                    },
-                    TokenKind::Keyword(Keyword::As) => ":".to_string(),
-                    TokenKind::Keyword(Keyword::If) => "if".to_string(),
-                    TokenKind::Keyword(Keyword::Else) => "} else".to_string(),
-                    TokenKind::Keyword(Keyword::Do) => "{".to_string(),
-                    TokenKind::Keyword(Keyword::End) => "}".to_string(),
-                    TokenKind::Keyword(Keyword::Derive) => format!("#[{}]", node_data),
-                    TokenKind::Keyword(Keyword::Implements) => "impl".to_string(),
-                    TokenKind::Keyword(Keyword::For) => "for".to_string(),
-                    TokenKind::Keyword(Keyword::Function) => "fn".to_string(),
-                    TokenKind::Keyword(Keyword::PublicFunction) => "pub fn".to_string(),
-                    TokenKind::Keyword(Keyword::Let) => "let".to_string(),
-                    TokenKind::Keyword(Keyword::Borrow) => "&".to_string(),
-                    TokenKind::Keyword(Keyword::Own) => "*".to_string(),
-                    TokenKind::Keyword(Keyword::Match) => "match".to_string(),
-                    TokenKind::TypeWithGeneric => str::replace(node_data, "\n", ";\n"),
-                    TokenKind::Macro | TokenKind::MacroRules => node_data.to_string(),
-                    TokenKind::MacroBody => node_data.to_string(),
-                    TokenKind::FunctionCall => node_data.to_string(),
-                    TokenKind::Equal => "==".to_string(),
-                    TokenKind::Not => "!".to_string(),
-                    TokenKind::NotEqual => "!=".to_string(),
-                    TokenKind::OpenParen => "(".to_string(),
-                    TokenKind::CloseParen => str::replace(node_data, "\n", ";\n"),
-                    TokenKind::Dot => ".".to_string(),
-                    TokenKind::Whitespace => " ".to_string(),
-                    TokenKind::NewLine => "\n".to_string(),
-                    TokenKind::Comma => ",".to_string(),
-                    TokenKind::Assign => "=".to_string(),
-                    TokenKind::DoubleDot => ":".to_string(),
-                    TokenKind::NamespaceSeparator => "::".to_string(),
-                    TokenKind::FatArrow => "=>".to_string(),
-                    TokenKind::Identifier => str::replace(node_data, "\n", ";\n"),
-                    TokenKind::Collection(Collection::Array) => str::replace(node_data, "\n", ";\n"),
-                    TokenKind::Collection(Collection::Tuple) => str::replace(node_data, "\n", ";\n"),
-                    TokenKind::Collection(Collection::HashMap) => {
-                        let raw_items = node_data.split(",").collect::<Vec<_>>();
-                        let mut items: Vec<String> = vec![];
-                        for item in raw_items.iter() {
-                            let replaced = 
-                                item
-                                    .replace("=","")
-                                    .replace("{","")
-                                    .replace("}","");
-
-                            let trimmed = replaced.trim().to_string();
-                            let vec_pair = trimmed.split(":").map(|s| s.to_string()).collect::<Vec<_>>();
-
-                            items.push(
-                                format!(r#"("{}", {})"#, vec_pair[0].clone(), vec_pair[1].clone())
-                            )
-                        }
-
-                        let mut new_line = false;
-                        if node_data.find("\n").is_some() {
-                            new_line = true;
-                        }
-                        format!(
-                            ": std::collections::HashMap<&str, _> = [{}].iter().cloned().collect(){}",
-                            items.join(","),
-                            if new_line { ";\n" } else { "" })
-                    },
-                    TokenKind::Operator(Operator::Add) => "+".to_string(),
-                    TokenKind::Operator(Operator::Minus) => "-".to_string(),
-                    TokenKind::Operator(Operator::Multiply) => "*".to_string(),
-                    TokenKind::Operator(Operator::Divide) => "/".to_string(),
-                    TokenKind::Operator(Operator::Mod) => "%".to_string(),
-                    TokenKind::Literal(LiteralKind::Boolean) => {
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("Boolean", "bool")
-                    },
-                    TokenKind::Literal(LiteralKind::String) => str::replace(node_data, "\n", ";\n"),
-                    TokenKind::Literal(LiteralKind::Int32) =>{
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("Int32", "i32")
-                    },
-                    TokenKind::Literal(LiteralKind::Int64) =>{
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("Int64", "i64")
-                    },
-                    TokenKind::Literal(LiteralKind::UInt32) => {
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("UInt32", "u32")
-                    },
-                    TokenKind::Literal(LiteralKind::UInt64) => {
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("UInt64", "u64")
-                    },
-                    TokenKind::Literal(LiteralKind::Float64) => {
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("Float64", "f64")
-                    },
-                    TokenKind::Literal(LiteralKind::Float32) => {
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("Float32", "f32")
-                    },
-                    TokenKind::Literal(LiteralKind::Usize) => {
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("Float32", "f32")
-                    },
-                    TokenKind::Literal(LiteralKind::Isize) => {
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("Isize", "isize")
+use crate::ast::{
+    Module, Item, Struct, Field, Enum, Trait, Impl, Function, BlockParam, MacroRules, Type, Path, Block, Stmt,
+    Let, Expr, ExprKind, Literal, UnaryOp, BinaryOp, Arg, Delimiter, MacroCall, MacroArgs, If, ElseBranch, Match,
+    Closure, Pattern, PatternKind, STRUCT_DERIVES, ENUM_DERIVES
+};
+use crate::printer::{ self, Printer, PrintOptions };
+
+const ORDERABLE_TYPES: &[&str] = &[
+    "bool", "char", "String", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize"
+];
+
+pub fn transpile(module: &Module) -> String {
+    transpile_with_options(module, PrintOptions::default())
+}
+
+pub fn transpile_with_options(module: &Module, options: PrintOptions) -> String {
+    let mut transpiler = Transpiler::new(options);
+    transpiler.items(&module.items);
+    transpiler.printer.finish()
+}
+
+struct Transpiler {
+    printer: Printer,
+}
+
+impl Transpiler {
+    fn new(options: PrintOptions) -> Transpiler {
+        Transpiler { printer: Printer::new(options) }
+    }
+
+    fn line(&mut self, text: &str) {
+        self.printer.line(text);
+    }
+
+    fn indented<F: FnOnce(&mut Transpiler)>(&mut self, write: F) {
+        self.printer.indent();
+        write(self);
+        self.printer.dedent();
+    }
+
+    fn separate_items(&mut self) {
+        if self.printer.options.blank_line_between_items {
+            self.printer.blank_line();
+        }
+    }
+
+    fn items(&mut self, items: &[Item]) {
+        let mut previous: Option<&Item> = None;
+        for item in items.iter().filter(|item| !matches!(item, Item::Error(_))) {
+            if previous.is_some_and(|previous| is_block_item(previous) || is_block_item(item)) {
+                self.separate_items();
+            }
+            self.item(item);
+            previous = Some(item);
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        match item {
+            Item::Module(module) => {
+                self.line(&format!("{}mod {} {{", visibility(module.public), module.name.as_deref().unwrap_or("")));
+                self.indented(|transpiler| transpiler.items(&module.items));
+                self.line("}");
+            },
+            Item::Struct(item) => self.structure(item),
+            Item::Enum(item) => self.enumeration(item),
+            Item::Trait(item) => self.trait_definition(item),
+            Item::Impl(item) => self.implementation(item),
+            Item::Function(function) => self.function(function, true),
+            Item::Use(item) => self.line(&format!("{}use {};", visibility(item.public), item.path)),
+            Item::ExternCrate(item) => self.line(&format!("extern crate {};", item.name)),
+            Item::MacroRules(item) => self.macro_rules(item),
+            Item::Stmt(stmt) => self.stmt(stmt),
+            Item::Error(_) => {},
+        }
+    }
+
+    fn structure(&mut self, item: &Struct) {
+        let mut derives = item.derives.clone();
+        let mut excluded = item.excluded_derives.clone();
+        let default_impl = item.fields.iter().any(|field| field.default.is_some()) &&
+            !excluded.iter().any(|name| name == "Default");
+        if default_impl {
+            derives.retain(|name| name != "Default");
+            excluded.push("Default".to_string());
+        }
+        self.derives(STRUCT_DERIVES, &derives, &excluded);
+        self.line(&format!("{}struct {} {{", visibility(item.public), item.name));
+        self.indented(|transpiler| {
+            for field in &item.fields {
+                transpiler.line(&format!("{},", field_declaration(field)));
+            }
+        });
+        self.line("}");
+        if default_impl {
+            self.separate_items();
+            self.default_impl(item);
+        }
+    }
+
+    fn default_impl(&mut self, item: &Struct) {
+        let fields = item.fields
+            .iter()
+            .map(|field| {
+                let value = match &field.default {
+                    Some(default) => self.typed_expression(default, Some(&field.ty)),
+                    None => "Default::default()".to_string(),
+                };
+                format!("{}: {},", field.name, value)
+            })
+            .collect::<Vec<_>>();
+        self.line(&format!("impl Default for {} {{", item.name));
+        self.indented(|transpiler| {
+            transpiler.line("fn default() -> Self {");
+            transpiler.indented(|transpiler| {
+                transpiler.line("Self {");
+                transpiler.indented(|transpiler| {
+                    for field in &fields {
+                        transpiler.line(field);
+                    }
+                });
+                transpiler.line("}");
+            });
+            transpiler.line("}");
+        });
+        self.line("}");
+    }
+
+    fn enumeration(&mut self, item: &Enum) {
+        let default = default_variant(item);
+        let mut excluded = item.excluded_derives.clone();
+        if default.is_none() {
+            excluded.push("Default".to_string());
+        }
+        let payloads = item.variants.iter().flat_map(|variant| &variant.fields).collect::<Vec<_>>();
+        if !payloads.iter().all(|ty| orderable(ty, false)) {
+            excluded.extend(vec!["Eq".to_string(), "Ord".to_string()]);
+        }
+        if !payloads.iter().all(|ty| orderable(ty, true)) {
+            excluded.push("PartialOrd".to_string());
+        }
+        self.derives(ENUM_DERIVES, &item.derives, &excluded);
+        self.line(&format!("{}enum {} {{", visibility(item.public), item.name));
+        self.indented(|transpiler| {
+            for variant in &item.variants {
+                if default == Some(&variant.name) && !excluded.iter().any(|name| name == "Default") {
+                    transpiler.line("#[default]");
+                }
+                if variant.fields.is_empty() {
+                    transpiler.line(&format!("{},", variant.name));
+                } else {
+                    let fields = variant.fields.iter().map(rust_type).collect::<Vec<_>>();
+                    transpiler.line(&format!("{}({}),", variant.name, fields.join(", ")));
+                }
+            }
+        });
+        self.line("}");
+    }
+
+    fn derives(&mut self, defaults: &[&str], derives: &[String], excluded: &[String]) {
+        let mut merged = defaults
+            .iter()
+            .filter(|default| !excluded.iter().any(|name| name == *default))
+            .map(|default| default.to_string())
+            .collect::<Vec<_>>();
+        for derive in derives {
+            if !merged.contains(derive) {
+                merged.push(derive.clone());
+            }
+        }
+        if !merged.is_empty() {
+            self.line(&format!("#[derive({})]", merged.join(", ")));
+        }
+    }
+
+    fn trait_definition(&mut self, item: &Trait) {
+        self.line(&format!("{}trait {} {{", visibility(item.public), item.name));
+        self.indented(|transpiler| {
+            for field in &item.fields {
+                transpiler.line(&format!("{};", accessor_signature(field, false)));
+                if field.mutable {
+                    transpiler.line(&format!("{};", accessor_signature(field, true)));
+                }
+            }
+            for function in &item.functions {
+                transpiler.separate_items();
+                transpiler.function(function, false);
+            }
+        });
+        self.line("}");
+    }
+
+    fn implementation(&mut self, item: &Impl) {
+        match &item.trait_name {
+            Some(trait_name) => self.line(&format!("impl {} for {} {{", path(trait_name), rust_type(&item.self_ty))),
+            None => self.line(&format!("impl {} {{", rust_type(&item.self_ty))),
+        }
+        self.indented(|transpiler| {
+            for field in &item.accessors {
+                let mutability = if field.mutable { vec![false, true] } else { vec![false] };
+                for mutable in mutability {
+                    transpiler.separate_items();
+                    transpiler.line(&format!("{} {{", accessor_signature(field, mutable)));
+                    transpiler.indented(|transpiler| {
+                        transpiler.line(&format!("&{}self.{}", if mutable { "mut " } else { "" }, field.name))
+                    });
+                    transpiler.line("}");
+                }
+            }
+            for function in &item.functions {
+                transpiler.separate_items();
+                transpiler.function(function, true);
+            }
+        });
+        self.line("}");
+    }
+
+    fn function(&mut self, function: &Function, allow_public: bool) {
+        let mut params = vec![];
+        if let Some(self_param) = &function.self_param {
+            let self_param = match (self_param.borrow, self_param.mutable) {
+                (true, true) => "&mut self",
+                (true, false) => "&self",
+                (false, true) => "mut self",
+                (false, false) => "self",
+            };
+            params.push(self_param.to_string());
+        }
+        for param in &function.params {
+            params.push(format!("{}{}: {}", if param.mutable { "mut " } else { "" }, param.name, rust_type(&param.ty)));
+        }
+        let generics = match &function.block_param {
+            Some(block_param) => {
+                let mutable = if function.body.is_some() { "mut " } else { "" };
+                params.push(format!("{}{}: F", mutable, block_param.name));
+                format!("<F: {}>", fn_trait(block_param))
+            },
+            None => String::new(),
+        };
+        let return_ty = function.return_ty.as_ref().map_or(String::new(), |ty| format!(" -> {}", rust_type(ty)));
+        let signature = format!(
+            "{}fn {}{}({}){}",
+            visibility(function.public && allow_public), function.name, generics, params.join(", "), return_ty
+        );
+
+        match &function.body {
+            Some(body) => {
+                self.line(&format!("{} {{", signature));
+                self.indented(|transpiler| transpiler.body(&body.stmts, function.return_ty.is_some()));
+                self.line("}");
+            },
+            None => self.line(&format!("{};", signature)),
+        }
+    }
+
+    fn macro_rules(&mut self, item: &MacroRules) {
+        self.line(&format!("macro_rules! {} {{", item.name));
+        self.indented(|transpiler| {
+            for line in printer::dedent_lines(&item.body) {
+                transpiler.line(line);
+            }
+        });
+        self.line("}");
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn body(&mut self, stmts: &[Stmt], tail: bool) {
+        match stmts.split_last() {
+            Some((Stmt::Expr(last), stmts)) if tail && yields_value(last) => {
+                self.stmts(stmts);
+                let text = self.tail_expression(last);
+                self.line(&text);
+            },
+            _ => self.stmts(stmts),
+        }
+    }
+
+    fn tail_expression(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Return(Some(value)) => self.expression(value),
+            _ => self.expression(expr),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let(binding) => {
+                let text = self.let_statement(binding);
+                self.line(&text);
+            },
+            Stmt::Expr(expr) => {
+                let text = match &expr.kind {
+                    ExprKind::If(if_expr) => self.if_expression(if_expr, false),
+                    ExprKind::Match(match_expr) if arms_yield_values(match_expr) => {
+                        format!("{};", self.match_expression(match_expr, false))
                     },
-                    TokenKind::Literal(LiteralKind::Byte) => {
-                        let data_type = str::replace(node_data, "\n", ";\n");
-                        data_type.replace("Byte", "u8")
+                    ExprKind::Match(match_expr) => self.match_expression(match_expr, false),
+                    ExprKind::For(_) |
+                    ExprKind::While(_) |
+                    ExprKind::Loop(_) => self.expression(expr),
+                    _ => format!("{};", self.expression(expr)),
+                };
+                self.line(&text);
+            },
+            Stmt::Error(_) => {},
+        }
+    }
+
+    fn let_statement(&self, binding: &Let) -> String {
+        let name = format!("{}{}", if binding.mutable { "mut " } else { "" }, binding.name);
+        match (&binding.ty, &binding.value.kind) {
+            (Some(ty), ExprKind::HashMap(entries)) => {
+                format!("let {}: {} = {};", name, rust_type(ty), self.hash_map(entries, Some(ty)))
+            },
+            (Some(ty), _) => format!("let {}: {} = {};", name, rust_type(ty), self.expression(&binding.value)),
+            (None, _) => format!("let {} = {};", name, self.expression(&binding.value)),
+        }
+    }
+
+    fn expression(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(literal) => literal_text(literal),
+            ExprKind::Path(expr_path) => path(expr_path),
+            ExprKind::Call { callee, args } => format!("{}({})", self.expression(callee), self.arguments(args)),
+            ExprKind::MethodCall { receiver, method, generics, args } => {
+                let turbofish = if generics.is_empty() {
+                    String::new()
+                } else {
+                    format!("::<{}>", generics.iter().map(rust_type).collect::<Vec<_>>().join(", "))
+                };
+                format!("{}.{}{}({})", self.expression(receiver), method, turbofish, self.arguments(args))
+            },
+            ExprKind::Field { receiver, name } => format!("{}.{}", self.expression(receiver), name),
+            ExprKind::Struct { path: struct_path, fields } => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, value)| match &value.kind {
+                        ExprKind::Path(value_path) if value_path.segments == [name.as_str()] => name.clone(),
+                        _ => format!("{}: {}", name, self.expression(value)),
+                    })
+                    .collect::<Vec<_>>();
+                if fields.is_empty() {
+                    format!("{} {{}}", path(struct_path))
+                } else {
+                    format!("{} {{ {} }}", path(struct_path), fields.join(", "))
+                }
+            },
+            ExprKind::Index { receiver, index } => {
+                format!("{}[{}]", self.expression(receiver), self.expression(index))
+            },
+            ExprKind::Try(inner) => format!("{}?", self.expression(inner)),
+            ExprKind::Unary { op, operand } => format!("{}{}", unary_operator(*op), self.expression(operand)),
+            ExprKind::Binary { op, left, right } => {
+                format!("{} {} {}", self.expression(left), binary_operator(*op), self.expression(right))
+            },
+            ExprKind::Assign { op, target, value } => format!(
+                "{} {}= {}",
+                self.expression(target),
+                op.map_or("", binary_operator),
+                self.expression(value)
+            ),
+            ExprKind::Paren(inner) => format!("({})", self.expression(inner)),
+            ExprKind::Array(items) => format!("[{}]", self.expressions(items)),
+            ExprKind::Tuple(items) if items.len() == 1 => format!("({},)", self.expression(&items[0])),
+            ExprKind::Tuple(items) => format!("({})", self.expressions(items)),
+            ExprKind::HashMap(entries) => self.hash_map(entries, None),
+            ExprKind::Closure(closure) => self.closure(closure),
+            ExprKind::MacroCall(call) => self.macro_call(call),
+            ExprKind::If(if_expr) => self.if_expression(if_expr, true),
+            ExprKind::Match(match_expr) => self.match_expression(match_expr, true),
+            ExprKind::For(for_expr) => format!(
+                "{}for {} in {} {}",
+                label_declaration(&for_expr.label),
+                pattern(&for_expr.pattern),
+                self.expression(&for_expr.iterable),
+                self.block(&for_expr.body, false)
+            ),
+            ExprKind::While(while_expr) => format!(
+                "{}while {} {}",
+                label_declaration(&while_expr.label),
+                self.expression(&while_expr.condition),
+                self.block(&while_expr.body, false)
+            ),
+            ExprKind::Loop(loop_expr) => {
+                format!("{}loop {}", label_declaration(&loop_expr.label), self.block(&loop_expr.body, false))
+            },
+            ExprKind::Range { start, end, inclusive } => format!(
+                "{}{}{}",
+                start.as_ref().map_or(String::new(), |start| self.expression(start)),
+                if *inclusive { "..=" } else { ".." },
+                end.as_ref().map_or(String::new(), |end| self.expression(end))
+            ),
+            ExprKind::Return(None) => "return".to_string(),
+            ExprKind::Return(Some(value)) => format!("return {}", self.expression(value)),
+            ExprKind::Break { label, value } => {
+                let mut text = format!("break{}", label_reference(label));
+                if let Some(value) = value {
+                    text.push_str(&format!(" {}", self.expression(value)));
+                }
+                text
+            },
+            ExprKind::Continue { label } => format!("continue{}", label_reference(label)),
+        }
+    }
+
+    fn expressions(&self, exprs: &[Expr]) -> String {
+        exprs.iter().map(|expr| self.expression(expr)).collect::<Vec<_>>().join(", ")
+    }
+
+    fn arguments(&self, args: &[Arg]) -> String {
+        args
+            .iter()
+            .map(|arg| self.expression(&arg.value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn closure(&self, closure: &Closure) -> String {
+        let params = closure.params
+            .iter()
+            .map(|param| {
+                let name = format!("{}{}", if param.mutable { "mut " } else { "" }, param.name);
+                match &param.ty {
+                    Some(ty) => format!("{}: {}", name, rust_type(ty)),
+                    None => name,
+                }
+            })
+            .collect::<Vec<_>>();
+        let head = format!("{}|{}|", if closure.capture_by_move { "move " } else { "" }, params.join(", "));
+        match (&closure.return_ty, closure.body.stmts.as_slice()) {
+            (None, [Stmt::Expr(expr)]) if !is_block_expression(expr) => format!("{} {}", head, self.tail_expression(expr)),
+            (Some(ty), _) => format!("{} -> {} {}", head, rust_type(ty), self.block(&closure.body, true)),
+            (None, _) => format!("{} {}", head, self.block(&closure.body, true)),
+        }
+    }
+
+    fn hash_map(&self, entries: &[(Expr, Expr)], ty: Option<&Type>) -> String {
+        if entries.is_empty() {
+            return "HashMap::new()".to_string()
+        }
+        let (key_ty, value_ty) = match ty {
+            Some(ty) if last_segment(&ty.name) == "HashMap" && ty.generics.len() == 2 => (Some(&ty.generics[0]), Some(&ty.generics[1])),
+            _ => (None, None),
+        };
+        let entries = entries
+            .iter()
+            .map(|(key, value)| {
+                format!("({}, {})", self.typed_expression(key, key_ty), self.typed_expression(value, value_ty))
+            })
+            .collect::<Vec<_>>();
+        format!("HashMap::from([{}])", entries.join(", "))
+    }
+
+    fn typed_expression(&self, expr: &Expr, ty: Option<&Type>) -> String {
+        match (&expr.kind, ty) {
+            (ExprKind::HashMap(entries), _) => self.hash_map(entries, ty),
+            (ExprKind::Literal(Literal::String(text)), Some(ty)) if last_segment(&ty.name) == "String" => {
+                format!("String::from({})", text)
+            },
+            _ => self.expression(expr),
+        }
+    }
+
+    fn macro_call(&self, call: &MacroCall) -> String {
+        let args = match &call.args {
+            MacroArgs::Exprs(exprs) => self.expressions(exprs),
+            MacroArgs::Tokens(tokens) => tokens.clone(),
+        };
+        match call.delimiter {
+            Delimiter::Paren => format!("{}!({})", call.name, args),
+            Delimiter::Bracket => format!("{}![{}]", call.name, args),
+            Delimiter::Brace => format!("{}! {{{}}}", call.name, args),
+        }
+    }
+
+    fn if_expression(&self, if_expr: &If, tail: bool) -> String {
+        let then_branch = self.block(&if_expr.then_branch, tail);
+        let mut text = format!("if {} {}", self.expression(&if_expr.condition), then_branch);
+        match &if_expr.else_branch {
+            Some(ElseBranch::If(else_if)) => text.push_str(&format!(" else {}", self.if_expression(else_if, tail))),
+            Some(ElseBranch::Block(block)) => text.push_str(&format!(" else {}", self.block(block, tail))),
+            None => {},
+        }
+        text
+    }
+
+    fn match_expression(&self, match_expr: &Match, tail: bool) -> String {
+        let mut nested = Transpiler::new(self.printer.options);
+        nested.line(&format!("match {} {{", self.expression(&match_expr.scrutinee)));
+        nested.indented(|transpiler| {
+            for arm in &match_expr.arms {
+                let mut text = pattern(&arm.pattern);
+                if let Some(guard) = &arm.guard {
+                    text.push_str(&format!(" if {}", transpiler.expression(guard)));
+                }
+                match arm.body.stmts.as_slice() {
+                    [Stmt::Expr(expr)] if !is_block_expression(expr) => {
+                        text.push_str(&format!(" => {},", transpiler.expression(expr)))
                     },
-                    TokenKind::Lexeme(Lexeme::String) => str::replace(node_data, "\n", ";\n"),
-                    TokenKind::Keyword(Keyword::Return) => "return".to_string(),
-                    _ => "".to_string()
+                    _ => text.push_str(&format!(" => {}", transpiler.block(&arm.body, tail))),
                 }
-            );
+                transpiler.line(&text);
+            }
+        });
+        nested.line("}");
+        nested.printer.finish().trim_end_matches('\n').to_string()
+    }
+
+    fn block(&self, block: &Block, tail: bool) -> String {
+        let mut nested = Transpiler::new(self.printer.options);
+        nested.line("{");
+        nested.indented(|transpiler| transpiler.body(&block.stmts, tail));
+        nested.line("}");
+        nested.printer.finish().trim_end_matches('\n').to_string()
+    }
+}
+
+fn is_block_item(item: &Item) -> bool {
+    match item {
+        Item::Stmt(Stmt::Expr(expr)) => is_block_expression(expr),
+        Item::Stmt(_) | Item::Use(_) | Item::ExternCrate(_) | Item::Error(_) => false,
+        _ => true,
+    }
+}
+
+fn is_block_expression(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::If(_) | ExprKind::Match(_) | ExprKind::For(_) | ExprKind::While(_) | ExprKind::Loop(_)
+    )
+}
+
+fn yields_value(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::If(if_expr) => if_yields_value(if_expr),
+        ExprKind::Assign { .. } |
+        ExprKind::For(_) |
+        ExprKind::While(_) |
+        ExprKind::Loop(_) |
+        ExprKind::Return(None) |
+        ExprKind::Break { .. } |
+        ExprKind::Continue { .. } => false,
+        _ => true,
+    }
+}
+
+fn if_yields_value(if_expr: &If) -> bool {
+    let else_yields = match &if_expr.else_branch {
+        Some(ElseBranch::If(else_if)) => if_yields_value(else_if),
+        Some(ElseBranch::Block(block)) => block_yields_value(block),
+        None => false,
+    };
+    else_yields && block_yields_value(&if_expr.then_branch)
+}
+
+fn block_yields_value(block: &Block) -> bool {
+    matches!(block.stmts.last(), Some(Stmt::Expr(expr)) if yields_value(expr))
+}
+
+fn arms_yield_values(match_expr: &Match) -> bool {
+    match_expr.arms
+        .iter()
+        .any(|arm| matches!(arm.body.stmts.as_slice(), [Stmt::Expr(expr)] if !is_block_expression(expr)))
+}
+
+fn pattern(pattern_node: &Pattern) -> String {
+    match &pattern_node.kind {
+        PatternKind::Wildcard => "_".to_string(),
+        PatternKind::Literal(literal) => literal_text(literal),
+        PatternKind::Path(pattern_path) => path(pattern_path),
+        PatternKind::Tuple(fields) => {
+            let fields = fields.iter().map(pattern).collect::<Vec<_>>();
+            format!("({})", fields.join(", "))
+        },
+        PatternKind::TupleStruct(pattern_path, fields) => {
+            let fields = fields.iter().map(pattern).collect::<Vec<_>>();
+            format!("{}({})", path(pattern_path), fields.join(", "))
+        },
+    }
+}
+
+fn label_declaration(label: &Option<String>) -> String {
+    label.as_ref().map_or(String::new(), |label| format!("'{}: ", label))
+}
+
+fn label_reference(label: &Option<String>) -> String {
+    label.as_ref().map_or(String::new(), |label| format!(" '{}", label))
+}
+
+fn literal_text(literal: &Literal) -> String {
+    match literal {
+        Literal::Integer(text) | Literal::Float(text) | Literal::String(text) | Literal::Char(text) => text.clone(),
+        Literal::Boolean(value) => value.to_string(),
+    }
+}
+
+fn unary_operator(op: UnaryOp) -> &'static str {
+    match op {
+        UnaryOp::Not => "!",
+        UnaryOp::Negate => "-",
+        UnaryOp::Borrow => "&",
+        UnaryOp::BorrowMut => "&mut ",
+        UnaryOp::Own => "*",
+    }
+}
+
+fn binary_operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::Modulo => "%",
+        BinaryOp::Equal => "==",
+        BinaryOp::NotEqual => "!=",
+        BinaryOp::Less => "<",
+        BinaryOp::LessEqual => "<=",
+        BinaryOp::Greater => ">",
+        BinaryOp::GreaterEqual => ">=",
+        BinaryOp::And => "&&",
+        BinaryOp::Or => "||",
+    }
+}
+
+fn field_declaration(field: &Field) -> String {
+    format!("{}{}: {}", visibility(field.public), field.name, rust_type(&field.ty))
+}
+
+fn default_variant(item: &Enum) -> Option<&String> {
+    match item.variants.iter().find(|variant| variant.default) {
+        Some(variant) if variant.fields.is_empty() => Some(&variant.name),
+        Some(_) => None,
+        None => item.variants.iter().find(|variant| variant.fields.is_empty()).map(|variant| &variant.name),
+    }
+}
+
+fn orderable(ty: &Type, allow_floats: bool) -> bool {
+    match rust_type_name(&ty.name) {
+        "Option" | "Vec" | "Box" | "Result" => ty.generics.iter().all(|ty| orderable(ty, allow_floats)),
+        "f32" | "f64" => allow_floats && ty.generics.is_empty(),
+        name => ty.generics.is_empty() && ORDERABLE_TYPES.contains(&name),
+    }
+}
+
+fn accessor_signature(field: &Field, mutable: bool) -> String {
+    if mutable {
+        format!("fn {}_mut(&mut self) -> &mut {}", field.name, rust_type(&field.ty))
+    } else {
+        format!("fn {}(&self) -> &{}", field.name, rust_type(&field.ty))
+    }
+}
+
+fn visibility(public: bool) -> &'static str {
+    if public { "pub " } else { "" }
+}
+
+fn path(path: &Path) -> String {
+    path.segments.join("::")
+}
+
+fn fn_trait(block_param: &BlockParam) -> String {
+    let params = block_param.params.iter().map(rust_type).collect::<Vec<_>>();
+    let return_ty = block_param.return_ty.as_ref().map_or(String::new(), |ty| format!(" -> {}", rust_type(ty)));
+    format!("FnMut({}){}", params.join(", "), return_ty)
+}
+
+fn rust_type(ty: &Type) -> String {
+    let name = rust_type_name(&ty.name);
+    if ty.generics.is_empty() {
+        name.to_string()
+    } else {
+        let generics = ty.generics.iter().map(rust_type).collect::<Vec<_>>();
+        format!("{}<{}>", name, generics.join(", "))
+    }
+}
+
+fn last_segment(name: &str) -> &str {
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+fn rust_type_name(name: &str) -> &str {
+    match name {
+        "Boolean" => "bool",
+        "Int32" => "i32",
+        "Int64" => "i64",
+        "UInt32" => "u32",
+        "UInt64" => "u64",
+        "Float64" => "f64",
+        "Float32" => "f32",
+        "Usize" => "usize",
+        "Isize" => "isize",
+        "Char" => "char",
+        "Byte" => "u8",
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+fn transpile_source(source: &str) -> String {
+    use crate::parser;
+    use crate::source::FileId;
+
+    transpile(&parser::parse(FileId(0), source).unwrap())
+}
+
+#[test]
+fn test_transpile() {
+    let source = "\
+implements Cat do
+  public function full_name(borrow self, last_name as String) as Option<String> do
+    if last_name == \"\" then
+      return None
+    else do
+      return Some(format!(\"{} {}\", self.name, last_name))
+    end
+  end
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+impl Cat {
+    pub fn full_name(&self, last_name: String) -> Option<String> {
+        if last_name == \"\" {
+            None
+        } else {
+            Some(format!(\"{} {}\", self.name, last_name))
         }
-        syntax.push(transpile(node));
     }
-    syntax.join("")
-}
\ No newline at end of file
+}
+"
+    );
+}
+
+#[test]
+fn test_transpile_guards() {
+    let source = "\
+function check(age as Int32) as Boolean do
+  return false if age < 18
+  return false unless age > 0
+  unless age == 42 do
+    println!(\"not the answer\")
+  end
+  return true
+end
+
+function countdown(mutable count as Int32) do
+  loop do
+    break if count == 0
+    return unless count < 100
+    count -= 1
+  end
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+fn check(age: i32) -> bool {
+    if age < 18 {
+        return false;
+    }
+    if !(age > 0) {
+        return false;
+    }
+    if !(age == 42) {
+        println!(\"not the answer\");
+    }
+    true
+}
+
+fn countdown(mut count: i32) {
+    loop {
+        if count == 0 {
+            break;
+        }
+        if !(count < 100) {
+            return;
+        }
+        count -= 1;
+    }
+}
+"
+    );
+}
+
+#[test]
+fn test_transpile_derives() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+derive(Serialize, Debug)
+no derive(Default)
+struct Token do
+  text as String
+end
+
+no derive(Default, Ord, PartialOrd)
+derive(Hash)
+enum Colour do
+  Red
+end
+";
+    let rust = transpile_source(source);
+    assert!(rust.contains("#[derive(Debug, Clone, PartialEq, Serialize)]\nstruct Token {"));
+    assert!(rust.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash)]\nenum Colour {"));
+
+    let (_, diagnostics) = parser::parse_partial(FileId(0), "no derive(Hash)\nstruct Token do\nend\n");
+    assert_eq!(
+        diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>(),
+        ["`Hash` is not derived by default for a struct"]
+    );
+}
+
+#[test]
+fn test_transpile_enum_defaults() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+enum Shape do
+  Circle(Float64)
+  Square(Option<Float32>)
+  default Empty
+end
+
+enum Colour do
+  Red
+  Green
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+enum Shape {
+    Circle(f64),
+    Square(Option<f32>),
+    #[default]
+    Empty,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+enum Colour {
+    #[default]
+    Red,
+    Green,
+}
+"
+    );
+
+    let source = "\
+struct Point do
+  x as Int32
+end
+
+enum Event do
+  Moved(Point)
+  Tagged(HashMap<String, Int32>)
+end
+
+enum Count do
+  Many(Vec<Option<Int64>>)
+  One(Box<Char>)
+end
+";
+    let rust = transpile_source(source);
+    assert!(rust.contains("#[derive(Debug, Clone, PartialEq)]\nenum Event {"));
+    assert!(rust.contains("#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]\nenum Count {"));
+
+    let (_, diagnostics) = parser::parse_partial(FileId(0), "enum Shape do\n  default Circle(Float64)\n  default Empty\nend\n");
+    assert_eq!(
+        diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>(),
+        ["default variant `Circle` cannot have fields", "enum already has default variant `Circle`"]
+    );
+}
+
+#[test]
+fn test_transpile_pipelines() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+let total = items |> sum
+let name = \" Ada \" |> .trim().to_string() |> greet(\"Hello\")
+let shown = value |> format!(\"{}\")
+let sides = width + height |> double |> borrow
+let first = list |> own |> .first()
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+let total = sum(items);
+let name = greet(\" Ada \".trim().to_string(), \"Hello\");
+let shown = format!(\"{}\", value);
+let sides = &double(width + height);
+let first = (*list).first();
+"
+    );
+
+    let diagnostics = parser::parse(FileId(0), "let value = 4 |> 5\n").unwrap_err();
+    assert_eq!(diagnostics.iter().next().unwrap().message, "cannot pipe a value into this expression");
+}
+
+#[test]
+fn test_transpile_mutability() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+trait Counting do
+  mutable count as Int32
+end
+
+implements Counter do
+  function bump(mutable borrow self, mutable step as Int32) do
+    let mutable total = step
+    add(mutable borrow total, self.count |> mutable borrow)
+  end
+
+  function finish(mutable self) do
+  end
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+trait Counting {
+    fn count(&self) -> &i32;
+    fn count_mut(&mut self) -> &mut i32;
+}
+
+impl Counter {
+    fn bump(&mut self, mut step: i32) {
+        let mut total = step;
+        add(&mut total, &mut self.count);
+    }
+
+    fn finish(mut self) {
+    }
+}
+"
+    );
+
+    let (_, diagnostics) = parser::parse_partial(FileId(0), "struct Counter do\n  mutable count as Int32\nend\n");
+    assert_eq!(diagnostics.iter().next().unwrap().message, "struct field `count` cannot be `mutable`");
+}
+
+#[test]
+fn test_transpile_for_loops() {
+    let source = "\
+for index in 0..10 do
+  println!(\"{}\", index)
+end
+for (index, name) in names |> .iter().enumerate() do
+  for _ in 1..=index do
+    println!(\"{}\", name)
+  end
+end
+let rest = items[2..]
+let evens = 0..count * 2 |> .step_by(2)
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+for index in 0..10 {
+    println!(\"{}\", index);
+}
+
+for (index, name) in names.iter().enumerate() {
+    for _ in 1..=index {
+        println!(\"{}\", name);
+    }
+}
+
+let rest = items[2..];
+let evens = (0..count * 2).step_by(2);
+"
+    );
+    assert_eq!(transpile_source("implements Naming for Cat do\nend\n"), "impl Naming for Cat {\n}\n");
+}
+
+#[test]
+fn test_transpile_loops() {
+    let source = "\
+while count < 10 do
+  next if count == 3
+  count += 1
+end
+let found = outer: loop do
+  for item in items do
+    next outer if item == 0
+    break outer item if item > 9
+  end
+  break 0
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+while count < 10 {
+    if count == 3 {
+        continue;
+    }
+    count += 1;
+}
+
+let found = 'outer: loop {
+    for item in items {
+        if item == 0 {
+            continue 'outer;
+        }
+        if item > 9 {
+            break 'outer item;
+        }
+    }
+    break 0;
+};
+"
+    );
+}
+
+#[test]
+fn test_transpile_if_chains() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+if score > 90 then
+  grade = \"A\"
+elsif score > 75 do
+  grade = \"B\"
+else
+  grade = \"C\"
+end
+unless ready then
+  wait()
+end
+let size = if count == 0 then \"none\" elsif count < 10 then \"few\" else \"many\" end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+if score > 90 {
+    grade = \"A\";
+} else if score > 75 {
+    grade = \"B\";
+} else {
+    grade = \"C\";
+}
+
+if !ready {
+    wait();
+}
+
+let size = if count == 0 {
+    \"none\"
+} else if count < 10 {
+    \"few\"
+} else {
+    \"many\"
+};
+"
+    );
+
+    let (_, diagnostics) = parser::parse_partial(FileId(0), "if ready\n  go()\nend\n");
+    assert_eq!(diagnostics.len(), 1);
+}
+
+#[test]
+fn test_transpile_match() {
+    let source = "\
+let label = match count do
+  0 => \"none\"
+  n when n < 10 => do
+    log(n)
+    \"few\"
+  end
+  _ => \"many\"
+end
+match command do
+  Quit => exit()
+  Move(x, y) => do
+    step(x)
+    step(y)
+  end
+end
+function approve(state as State) do
+  match state do
+    Approved => \"approved\"
+    _ => \"pending\"
+  end
+  match state do
+    Approved => do
+      notify()
+      archive()
+    end
+  end
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+let label = match count {
+    0 => \"none\",
+    n if n < 10 => {
+        log(n);
+        \"few\"
+    }
+    _ => \"many\",
+};
+
+match command {
+    Quit => exit(),
+    Move(x, y) => {
+        step(x);
+        step(y);
+    }
+};
+
+fn approve(state: State) {
+    match state {
+        Approved => \"approved\",
+        _ => \"pending\",
+    };
+    match state {
+        Approved => {
+            notify();
+            archive();
+        }
+    }
+}
+"
+    );
+}
+
+#[test]
+fn test_transpile_hash_maps() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+let ages = {ada: 36, \"grace hopper\": 85,}
+let squares = {
+  1: 1,
+  2: square(2, 2),
+}
+let lookup = {key() => (1, 2), Color::Red => (0, 0)}
+let names as HashMap<String, HashMap<Int32, String>> = {
+  greek: {1: \"alpha\", 2: \"beta\"},
+  empty: {},
+}
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+let ages = HashMap::from([(\"ada\", 36), (\"grace hopper\", 85)]);
+let squares = HashMap::from([(1, 1), (2, square(2, 2))]);
+let lookup = HashMap::from([(key(), (1, 2)), (Color::Red, (0, 0))]);
+let names: HashMap<String, HashMap<i32, String>> = HashMap::from([\
+(String::from(\"greek\"), HashMap::from([(1, String::from(\"alpha\")), (2, String::from(\"beta\"))])), \
+(String::from(\"empty\"), HashMap::new())]);
+"
+    );
+
+    let rust = transpile_source("let ages as std::collections::HashMap<String, Int32> = {ada: 36}\n");
+    assert_eq!(rust, "let ages: std::collections::HashMap<String, i32> = HashMap::from([(String::from(\"ada\"), 36)]);\n");
+
+    let (_, diagnostics) = parser::parse_partial(FileId(0), "let ages = {ada 36}\n");
+    assert_eq!(diagnostics.len(), 1);
+}
+
+#[test]
+fn test_transpile_implicit_return() {
+    let source = "\
+function total(items as Vec<Int32>) as Int32 do
+  let sum = items
+    .iter()
+    .sum()
+  sum +
+    1
+end
+
+function label(count as Int32) as String do
+  return \"none\".to_string() if count == 0
+  match count do
+    1 => \"one\".to_string()
+    _ => return \"many\".to_string()
+  end
+end
+
+function log(message as String) do
+  message
+    |> borrow
+    |> println!(\"{}\")
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+fn total(items: Vec<i32>) -> i32 {
+    let sum = items.iter().sum();
+    sum + 1
+}
+
+fn label(count: i32) -> String {
+    if count == 0 {
+        return \"none\".to_string();
+    }
+    match count {
+        1 => \"one\".to_string(),
+        _ => return \"many\".to_string(),
+    }
+}
+
+fn log(message: String) {
+    println!(\"{}\", &message);
+}
+"
+    );
+}
+
+#[test]
+fn test_transpile_statement_tails() {
+    let source = "\
+let mutable total = 0
+items.each do |item|
+  println!(\"{}\", item)
+  return if item < 0
+end
+items.each do |item|
+  println!(\"{}\", item)
+  total += item
+end
+items.each do |item|
+  while total > item do
+    total -= item
+  end
+end
+items.each do |item|
+  for step in 0..item do
+    let half = if step % 2 == 0 then step / 2 else next end
+    let small = if half < 10 then half else break end
+    println!(\"{}\", small)
+  end
+end
+
+function first_positive(items as Vec<Int32>) as Int32 do
+  let mutable index = 0
+  loop do
+    return items[index] if items[index] > 0
+    index += 1
+  end
+end
+
+function sign(value as Int32) as Int32 do
+  if value < 0 then -1 elsif value > 0 then 1 else 0 end
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+let mut total = 0;
+items.each(|item| {
+    println!(\"{}\", item);
+    if item < 0 {
+        return;
+    }
+});
+items.each(|item| {
+    println!(\"{}\", item);
+    total += item;
+});
+items.each(|item| {
+    while total > item {
+        total -= item;
+    }
+});
+items.each(|item| {
+    for step in 0..item {
+        let half = if step % 2 == 0 {
+            step / 2
+        } else {
+            continue;
+        };
+        let small = if half < 10 {
+            half
+        } else {
+            break;
+        };
+        println!(\"{}\", small);
+    }
+});
+
+fn first_positive(items: Vec<i32>) -> i32 {
+    let mut index = 0;
+    loop {
+        if items[index] > 0 {
+            return items[index];
+        }
+        index += 1;
+    }
+}
+
+fn sign(value: i32) -> i32 {
+    if value < 0 {
+        -1
+    } else if value > 0 {
+        1
+    } else {
+        0
+    }
+}
+"
+    );
+}
+
+#[test]
+fn test_transpile_closures() {
+    use crate::parser;
+    use crate::source::FileId;
+
+    let source = "\
+function each(items as Vec<Int32>, &block(Int32)) do
+  for item in items do
+    block(item)
+  end
+end
+
+let double = fn(x) do x * 2 end
+let add = fn(a as Int32, mutable b as Int32) as Int32 do
+  b += a
+  b
+end
+let doubled = items.iter().map do |x| x * 2 end
+items.each do |item|
+  let label = format!(\"#{}\", item)
+  println!(\"{}\", label)
+end
+let handle = thread::spawn move do ||
+  println!(\"{}\", total)
+end
+let counter = move fn() do return count end
+let mutable total = 0
+each(items) do |item|
+  total += item
+end
+
+trait Visit do
+  function visit(borrow self, &block(Int32) as Boolean)
+end
+";
+    assert_eq!(
+        transpile_source(source),
+        "\
+fn each<F: FnMut(i32)>(items: Vec<i32>, mut block: F) {
+    for item in items {
+        block(item);
+    }
+}
+
+let double = |x| x * 2;
+let add = |a: i32, mut b: i32| -> i32 {
+    b += a;
+    b
+};
+let doubled = items.iter().map(|x| x * 2);
+items.each(|item| {
+    let label = format!(\"#{}\", item);
+    println!(\"{}\", label)
+});
+let handle = thread::spawn(move || println!(\"{}\", total));
+let counter = move || count;
+let mut total = 0;
+each(items, |item| total += item);
+
+trait Visit {
+    fn visit<F: FnMut(i32) -> bool>(&self, block: F);
+}
+"
+    );
+
+    let (_, diagnostics) = parser::parse_partial(FileId(0), "function f(&block, x as Int32) do\nend\n");
+    assert_eq!(
+        diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>(),
+        ["block parameter `block` must be the last parameter"]
+    );
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use crate::ast::{ Module, Item, Function, Stmt, Let, Expr, ExprKind, UnaryOp, Pattern, PatternKind, walk_lets };
+use crate::diagnostic::{ Diagnostic, Diagnostics };
+use crate::resolve;
+
+type EnumKey = Vec<String>;
+
+struct Context<'a> {
+    scope: &'a [String],
+    enums: &'a HashMap<EnumKey, Vec<String>>,
+    self_variants: Option<&'a Vec<String>>,
+    bindings: HashMap<String, Option<Vec<String>>>,
+}
+
+pub fn qualify(module: &mut Module) -> Diagnostics {
+    let mut enums = HashMap::new();
+    collect_enums(&module.items, &mut vec![], &mut enums);
+
+    let mut diagnostics = Diagnostics::new();
+    qualify_items(&mut module.items, &mut vec![], &enums, &mut diagnostics);
+    diagnostics
+}
+
+fn collect_enums(items: &[Item], scope: &mut Vec<String>, enums: &mut HashMap<EnumKey, Vec<String>>) {
+    for item in items {
+        match item {
+            Item::Enum(item) => {
+                let key = scope.iter().cloned().chain(Some(item.name.clone())).collect();
+                let variants = item.variants.iter().map(|variant| variant.name.clone()).collect();
+                enums.entry(key).or_insert(variants);
+            },
+            Item::Module(module) => {
+                scope.push(module.name.clone().unwrap_or_default());
+                collect_enums(&module.items, scope, enums);
+                scope.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+fn qualify_items(
+    items: &mut [Item],
+    scope: &mut Vec<String>,
+    enums: &HashMap<EnumKey, Vec<String>>,
+    diagnostics: &mut Diagnostics,
+) {
+    let mut context = Context { scope, enums, self_variants: None, bindings: HashMap::new() };
+    for item in items.iter_mut() {
+        if let Item::Stmt(stmt) = item {
+            context.bind_stmts(std::slice::from_mut(stmt));
+        }
+    }
+    for item in items.iter_mut() {
+        if let Item::Stmt(stmt) = item {
+            stmt.walk_mut(&mut |expr| context.qualify_match(expr, diagnostics));
+        }
+    }
+
+    for item in items {
+        match item {
+            Item::Function(function) => qualify_function(function, None, scope, enums, diagnostics),
+            Item::Impl(item) => {
+                for function in &mut item.functions {
+                    qualify_function(function, Some(&item.self_ty.name), scope, enums, diagnostics);
+                }
+            },
+            Item::Trait(item) => {
+                for function in &mut item.functions {
+                    qualify_function(function, None, scope, enums, diagnostics);
+                }
+            },
+            Item::Module(module) => {
+                scope.push(module.name.clone().unwrap_or_default());
+                qualify_items(&mut module.items, scope, enums, diagnostics);
+                scope.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+fn qualify_function(
+    function: &mut Function,
+    self_ty: Option<&str>,
+    scope: &[String],
+    enums: &HashMap<EnumKey, Vec<String>>,
+    diagnostics: &mut Diagnostics,
+) {
+    let body = match &mut function.body {
+        Some(body) => body,
+        None => return,
+    };
+    let mut context = Context { scope, enums, self_variants: None, bindings: HashMap::new() };
+    if let Some(self_ty) = self_ty {
+        context.self_variants = context.find_enum(&split_path(self_ty));
+        if function.self_param.is_some() && context.self_variants.is_some() {
+            context.bind("self", Some(vec!["Self".to_string()]));
+        }
+    }
+    for param in &function.params {
+        let ty = split_path(&param.ty.name);
+        let enum_path = context.find_enum(&ty).map(|_| ty);
+        context.bind(&param.name, enum_path);
+    }
+    context.bind_stmts(&mut body.stmts);
+    body.walk_mut(&mut |expr| context.qualify_match(expr, diagnostics));
+}
+
+impl<'a> Context<'a> {
+    fn find_enum(&self, segments: &[String]) -> Option<&'a Vec<String>> {
+        let enums = self.enums;
+        resolve::candidates(self.scope, segments)
+            .iter()
+            .find_map(|candidate| enums.get(candidate))
+    }
+
+    fn variants(&self, enum_path: &[String]) -> Option<&'a Vec<String>> {
+        match enum_path {
+            [name] if name == "Self" => self.self_variants,
+            _ => self.find_enum(enum_path),
+        }
+    }
+
+    fn bind(&mut self, name: &str, enum_path: Option<Vec<String>>) {
+        let binding = self.bindings.entry(name.to_string()).or_insert_with(|| enum_path.clone());
+        if *binding != enum_path {
+            *binding = None;
+        }
+    }
+
+    fn bind_let(&mut self, binding: &Let) {
+        let enum_path = match &binding.ty {
+            Some(ty) => {
+                let ty = split_path(&ty.name);
+                self.find_enum(&ty).map(|_| ty)
+            },
+            None => self.enum_of(&binding.value),
+        };
+        self.bind(&binding.name, enum_path);
+    }
+
+    fn bind_stmts(&mut self, stmts: &mut [Stmt]) {
+        walk_lets(stmts, &mut |binding| self.bind_let(binding));
+    }
+
+    fn enum_of(&self, expr: &Expr) -> Option<Vec<String>> {
+        match &expr.kind {
+            ExprKind::Path(path) if path.segments.len() == 1 => self.bindings.get(&path.segments[0]).cloned().flatten(),
+            ExprKind::Path(path) => self.variant_enum(&path.segments),
+            ExprKind::Call { callee, .. } => match &callee.kind {
+                ExprKind::Path(path) if path.segments.len() > 1 => self.variant_enum(&path.segments),
+                _ => None,
+            },
+            ExprKind::Unary { op: UnaryOp::Borrow, operand } |
+            ExprKind::Unary { op: UnaryOp::BorrowMut, operand } |
+            ExprKind::Unary { op: UnaryOp::Own, operand } => self.enum_of(operand),
+            ExprKind::Paren(expr) => self.enum_of(expr),
+            _ => None,
+        }
+    }
+
+    fn variant_enum(&self, segments: &[String]) -> Option<Vec<String>> {
+        let (variant, enum_path) = segments.split_last()?;
+        let variants = self.variants(enum_path)?;
+        if variants.contains(variant) {
+            Some(enum_path.to_vec())
+        } else {
+            None
+        }
+    }
+
+    fn qualify_match(&self, expr: &mut Expr, diagnostics: &mut Diagnostics) {
+        let item = match &mut expr.kind {
+            ExprKind::Match(item) => item,
+            _ => return,
+        };
+        let enum_path = match self.enum_of(&item.scrutinee) {
+            Some(enum_path) => enum_path,
+            None => return,
+        };
+        if let Some(variants) = self.variants(&enum_path) {
+            for arm in &mut item.arms {
+                qualify_pattern(&mut arm.pattern, &enum_path, variants, diagnostics);
+            }
+        }
+    }
+}
+
+fn qualify_pattern(pattern: &mut Pattern, enum_path: &[String], variants: &[String], diagnostics: &mut Diagnostics) {
+    let (path, fields) = match &mut pattern.kind {
+        PatternKind::Path(path) => (path, false),
+        PatternKind::TupleStruct(path, _) => (path, true),
+        _ => return,
+    };
+    if path.segments.len() != 1 {
+        return
+    }
+    let name = &path.segments[0];
+    if variants.contains(name) {
+        path.segments = enum_path.iter().cloned().chain(Some(name.clone())).collect();
+    } else if !fields && name.starts_with(char::is_uppercase) {
+        let message = format!("enum `{}` has no variant named `{}`", enum_path.join("::"), name);
+        diagnostics.push(Diagnostic::error(&message, pattern.span));
+    }
+}
+
+fn split_path(name: &str) -> Vec<String> {
+    name.split("::").map(|segment| segment.to_string()).collect()
+}
+
+#[test]
+fn test_qualify() {
+    use crate::parser;
+    use crate::source::FileId;
+    use crate::transpiler::transpile;
+
+    let source = "\
+module shapes do
+  enum Shape do
+    Circle(Float64)
+    Square(Float64)
+    Point
+  end
+end
+
+implements shapes::Shape do
+  function area(borrow self) as Float64 do
+    match self do
+      Circle(radius) => radius * radius * 3.14
+      Square(side) => side * side
+      Point => 0.0
+    end
+  end
+end
+
+function describe(shape as shapes::Shape) do
+  match shape do
+    Point => println!(\"point\")
+    _ => println!(\"shape\")
+  end
+end
+
+let circle = shapes::Shape::Circle(1.0)
+match borrow circle do
+  Circle(radius) when radius > 1.0 => println!(\"large\")
+  Triangle => println!(\"triangle\")
+  other => println!(\"other\")
+end
+";
+    let mut module = parser::parse(FileId(0), source).unwrap();
+    let diagnostics = qualify(&mut module);
+    let messages = diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect::<Vec<_>>();
+    assert_eq!(messages, ["enum `shapes::Shape` has no variant named `Triangle`"]);
+
+    let rust = transpile(&module);
+    assert!(rust.contains("Self::Circle(radius) => radius * radius * 3.14,"));
+    assert!(rust.contains("Self::Point => 0.0,"));
+    assert!(rust.contains("shapes::Shape::Point => println!(\"point\"),"));
+    assert!(rust.contains("shapes::Shape::Circle(radius) if radius > 1.0 => println!(\"large\"),"));
+    assert!(rust.contains("other => println!(\"other\"),"));
+}